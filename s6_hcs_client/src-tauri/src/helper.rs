@@ -1,7 +1,13 @@
+use rand::random;
+use s6_hcs_lib_crypto::crypto::progress::{CancelToken, Progress};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
 use std::net::TcpStream;
-use std::sync::{mpsc, mpsc::Sender};
-use std::thread::JoinHandle;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Mutex;
+use tauri::Window;
 use websocket::sync::Client;
 use websocket::ClientBuilder;
 
@@ -11,6 +17,9 @@ pub enum RequestProcessingError {
     BadRequest,
     ServerError,
     BadFile,
+    CorruptedFile,
+    PassphraseRequired,
+    Cancelled,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -22,6 +31,26 @@ pub enum OperationProgress {
     Downloading(u8),
 }
 
+// Removes the file once dropped, so early returns do not leave it behind
+pub struct TempFile(pub PathBuf);
+
+impl TempFile {
+    pub fn new() -> Self {
+        Self::in_dir(&std::env::temp_dir())
+    }
+
+    // next to its destination, so fs::rename does not cross file systems
+    pub fn in_dir(dir: &Path) -> Self {
+        Self(dir.join(format!(".s6_hcs_{:032x}", random::<u128>())))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        fs::remove_file(&self.0).unwrap_or_default();
+    }
+}
+
 pub fn connect(url: &str) -> Result<Client<TcpStream>, RequestProcessingError> {
     let client = match ClientBuilder::new(url) {
         Ok(client) => client,
@@ -34,28 +63,82 @@ pub fn connect(url: &str) -> Result<Client<TcpStream>, RequestProcessingError> {
     }
 }
 
-pub fn make_progress_reporter(
-    len: usize,
-    cb: Box<(dyn Fn(u8) + Send)>,
-) -> (Sender<Option<()>>, JoinHandle<()>) {
-    let (tx, rx) = mpsc::channel();
-    let handle = std::thread::spawn(move || {
-        let mut cnt = 0;
-        loop {
-            match rx.recv() {
-                Err(_) => return,
-                Ok(None) => return,
-                Ok(Some(_)) => {
-                    cnt += 1;
-                    if (cnt * 100 / len) < ((cnt + 1) * 100 / len) {
-                        cb((cnt * 100 / len + 1) as u8);
-                    }
-                    if cnt >= len {
-                        return;
-                    }
-                }
+// Emits the percentage of the current stage whenever it changes
+pub struct ProgressReporter {
+    window: Window,
+    event: String,
+    stage: fn(u8) -> OperationProgress,
+    last: AtomicU8,
+}
+
+impl ProgressReporter {
+    pub fn new(window: &Window, event: &str, stage: fn(u8) -> OperationProgress) -> Self {
+        window.emit(event, stage(0)).unwrap_or_default();
+        Self {
+            window: window.clone(),
+            event: event.to_owned(),
+            stage,
+            last: AtomicU8::new(0),
+        }
+    }
+}
+
+impl Progress for ProgressReporter {
+    fn update(&self, done: u64, total: u64) {
+        let percent = (done * 100).checked_div(total).unwrap_or(100).min(100) as u8;
+        if self.last.swap(percent, Ordering::Relaxed) != percent {
+            let stage = (self.stage)(percent);
+            self.window.emit(&self.event, stage).unwrap_or_default();
+        }
+    }
+}
+
+// Cancellation tokens of the running operations, by their event name
+#[derive(Default)]
+pub struct Operations(Mutex<HashMap<String, CancelToken>>);
+
+impl Operations {
+    pub fn start(&self, id: &str) -> Operation<'_> {
+        let cancel = CancelToken::new();
+        let mut running = self.0.lock().unwrap();
+        running.insert(id.to_owned(), cancel.clone());
+        Operation {
+            operations: self,
+            id: id.to_owned(),
+            cancel,
+        }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.0.lock().unwrap().get(id) {
+            Some(cancel) => {
+                cancel.cancel();
+                true
             }
+            None => false,
+        }
+    }
+}
+
+// Unregisters the operation once dropped
+pub struct Operation<'a> {
+    operations: &'a Operations,
+    id: String,
+    pub cancel: CancelToken,
+}
+
+impl Operation<'_> {
+    // a failure caused by cancelling is reported as such
+    pub fn error(&self, e: RequestProcessingError) -> RequestProcessingError {
+        match self.cancel.is_cancelled() {
+            true => RequestProcessingError::Cancelled,
+            false => e,
         }
-    });
-    (tx, handle)
+    }
+}
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        self.operations.0.lock().unwrap().remove(&self.id);
+    }
 }
@@ -5,11 +5,13 @@ mod helper;
 
 fn main() {
     tauri::Builder::default()
+        .manage(helper::Operations::default())
         .invoke_handler(tauri::generate_handler![
             server_calls::get_files,
             server_calls::upload,
             server_calls::download,
             server_calls::delete,
+            server_calls::cancel,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -17,14 +19,15 @@ fn main() {
 
 pub mod server_calls {
     use crate::helper::*;
-    use s6_hcs_lib_crypto::crypto::{
-        padding::{PaddingAlgorithm, PaddingPKSC7},
-        symmetric_algs::{DEALMode, DEAL128},
-    };
+    use s6_hcs_lib_crypto::crypto::container::{self, CipherId, Header, ModeId, PaddingId};
+    use s6_hcs_lib_crypto::crypto::kdf::ScryptParams;
+    use s6_hcs_lib_crypto::crypto::rng::OsRng;
+    use s6_hcs_lib_crypto::crypto::symmetric_algs::encrypt_stream;
     use s6_hcs_lib_transfer::{aux::*, file_exchange, key_exchange, messages::*};
-    use std::fs;
-    use std::path::PathBuf;
-    use tauri::Manager;
+    use std::fs::{self, File};
+    use std::io::{ErrorKind, Seek, SeekFrom};
+    use std::path::{Path, PathBuf};
+    use tauri::{Manager, State};
 
     use OperationProgress::*;
     use RequestProcessingError::*;
@@ -61,11 +64,17 @@ pub mod server_calls {
     #[tauri::command]
     pub async fn upload(
         app: tauri::AppHandle,
+        operations: State<'_, Operations>,
         url: &str,
         file: &str,
         event: &str,
+        cipher: CipherId,
+        mode: ModeId,
+        padding: PaddingId,
+        passphrase: Option<String>,
     ) -> Result<(), RequestProcessingError> {
         let window = app.get_window("main").unwrap();
+        let op = operations.start(event);
 
         window.emit(event, Connecting(0)).unwrap();
         let mut client = match connect(url) {
@@ -81,55 +90,89 @@ pub mod server_calls {
                 Ok(name) => name,
             },
         };
-        if !file_path.exists() {
-            return Err(BadFile);
-        }
-        let mut contents_dec = match fs::read(file_path) {
-            Ok(c) => c,
+        let mut file_dec = match File::open(file_path) {
+            Ok(f) => f,
+            Err(_) => return Err(BadFile),
+        };
+        let len = match file_dec.metadata() {
+            Ok(m) => m.len(),
             Err(_) => return Err(BadFile),
         };
+        // a passphrase key never leaves the client, the server gets an empty one
+        let passphrase = passphrase.filter(|p| !p.is_empty());
+        let key = match passphrase {
+            Some(_) => Vec::new(),
+            None => cipher.generate_key(&mut OsRng),
+        };
+        let padding = match mode {
+            ModeId::GCM | ModeId::Poly1305 => PaddingId::None,
+            _ => padding,
+        };
 
-        PaddingPKSC7::with_block_size(16).apply_padding(&mut contents_dec);
-        let contents_dec = u8_to_u128(contents_dec);
-        let key = DEAL128::generate_key();
-
-        let contents_enc = {
-            window.emit(event, Encrypting(0)).unwrap_or_default();
-            let w = window.clone();
-            let e = event.to_owned();
-            let (tx, handle) = make_progress_reporter(
-                contents_dec.len(),
-                Box::new(move |i| w.emit(e.as_str(), Encrypting(i)).unwrap_or_default()),
-            );
-            let contents_enc = DEALMode::RDH.encrypt(contents_dec, key, Some(tx.clone()));
-            tx.send(None).unwrap_or_default();
-            handle.join().unwrap_or_default();
+        let temp = TempFile::new();
+        {
+            let progress = ProgressReporter::new(&window, event, Encrypting);
+            let aad = file_name.as_bytes();
+            let encryptor = match &passphrase {
+                Some(p) => container::passphrase_encryptor(
+                    cipher,
+                    mode,
+                    padding,
+                    p.as_bytes(),
+                    ScryptParams::default(),
+                    aad,
+                    &mut OsRng,
+                ),
+                None => container::encryptor(cipher, mode, padding, &key, aad, &mut OsRng),
+            };
+            let mut encryptor = match encryptor {
+                Ok(e) => e,
+                Err(_) => return Err(BadRequest),
+            };
+            let encrypted = File::create(&temp.0).and_then(|mut file_enc| {
+                encrypt_stream(
+                    &mut encryptor,
+                    &mut file_dec,
+                    &mut file_enc,
+                    len,
+                    &progress,
+                    &op.cancel,
+                )
+            });
+            if let Err(_) = encrypted {
+                return Err(op.error(BadFile));
+            }
             window.emit(event, Encrypting(100)).unwrap_or_default();
-            contents_enc
+        }
+        let mut file_enc = match File::open(&temp.0) {
+            Ok(f) => f,
+            Err(_) => return Err(BadFile),
+        };
+        let len = match file_enc.metadata() {
+            Ok(m) => m.len(),
+            Err(_) => return Err(BadFile),
         };
 
         if let Err(_) = client.send_message(&serialize(Request::Upload)) {
             return Err(NoConnection);
         }
-        key_exchange::client_send(&mut client, key);
+        let progress = ProgressReporter::new(&window, event, Connecting);
+        if let Err(_) =
+            key_exchange::client_send(&mut client, &key, &mut OsRng, &progress, &op.cancel)
+        {
+            return Err(op.error(NoConnection));
+        }
         if let Err(_) = client.send_message(&serialize(file_name)) {
             return Err(NoConnection);
         }
 
         {
-            let contents_enc = u128_to_u8(contents_enc);
-            window.emit(event, Uploading(0)).unwrap_or_default();
-            let w = window.clone();
-            let e = event.to_owned();
-            let (tx, handle) = make_progress_reporter(
-                file_exchange::count_dataframes(&contents_enc),
-                Box::new(move |i| w.emit(e.as_str(), Uploading(i)).unwrap_or_default()),
-            );
-            if let Err(_) = file_exchange::send_file(&mut client, contents_enc, Some(tx.clone())) {
-                return Err(NoConnection);
+            let progress = ProgressReporter::new(&window, event, Uploading);
+            let sent =
+                file_exchange::send_file(&mut client, &mut file_enc, len, &progress, &op.cancel);
+            if let Err(_) = sent {
+                return Err(op.error(NoConnection));
             }
-            tx.send(None).unwrap_or_default();
-            handle.join().unwrap_or_default();
             window.emit(event, Uploading(100)).unwrap_or_default();
         }
 
@@ -147,12 +190,15 @@ pub mod server_calls {
     #[tauri::command]
     pub async fn download(
         app: tauri::AppHandle,
+        operations: State<'_, Operations>,
         url: &str,
         id: &str,
         file: &str,
         event: &str,
+        passphrase: Option<String>,
     ) -> Result<(), RequestProcessingError> {
         let window = app.get_window("main").unwrap();
+        let op = operations.start(event);
 
         window.emit(event, Connecting(0)).unwrap();
         let id: u128 = match id.parse() {
@@ -174,54 +220,105 @@ pub mod server_calls {
             CommFail => return Err(BadRequest),
         }
 
-        let key = key_exchange::client_receive(&mut client);
+        let progress = ProgressReporter::new(&window, event, Connecting);
+        let key = match key_exchange::client_receive(&mut client, &mut OsRng, &progress, &op.cancel)
+        {
+            Ok(key) => key,
+            Err(_) => return Err(op.error(NoConnection)),
+        };
+        let file_name: String = deserialize(client.recv_message());
 
-        let contents_enc = {
-            window.emit(event, Downloading(0)).unwrap_or_default();
-            let e = event.to_owned();
-            let w = window.clone();
+        let temp = TempFile::new();
+        {
             let size = match file_exchange::recv_file_len(&mut client) {
                 Ok(s) => s,
                 Err(_) => return Err(NoConnection),
             };
-            let (tx, handle) = make_progress_reporter(
-                size,
-                Box::new(move |i| w.emit(e.as_str(), Downloading(i)).unwrap_or_default()),
-            );
-            let contents = match file_exchange::recv_file(&mut client, size, Some(tx.clone())) {
-                Ok(c) => c,
-                Err(_) => return Err(NoConnection),
-            };
-            tx.send(None).unwrap_or_default();
-            handle.join().unwrap_or_default();
-            window.emit(event, Downloading(100)).unwrap_or_default();
-            u8_to_u128(contents)
-        };
-
-        let contents_dec = {
-            window.emit(event, Decrypting(0)).unwrap_or_default();
-            let e = event.to_owned();
-            let w = window.clone();
-            let (tx, handle) = make_progress_reporter(
-                contents_enc.len(),
-                Box::new(move |i: u8| w.emit(e.as_str(), Decrypting(i)).unwrap_or_default()),
-            );
-            let decrypted = match DEALMode::RDH.decrypt(contents_enc, key, Some(tx.clone())) {
-                Ok(dec) => dec,
+            let mut file_enc = match File::create(&temp.0) {
+                Ok(f) => f,
                 Err(_) => return Err(BadFile),
             };
-            tx.send(None).unwrap_or_default();
-            handle.join().unwrap_or_default();
-            window.emit(event, Decrypting(100)).unwrap_or_default();
-            decrypted
-        };
+            let progress = ProgressReporter::new(&window, event, Downloading);
+            let received =
+                file_exchange::recv_file(&mut client, &mut file_enc, size, &progress, &op.cancel);
+            if let Err(_) = received {
+                return Err(op.error(NoConnection));
+            }
+            window.emit(event, Downloading(100)).unwrap_or_default();
+        }
 
-        let mut dec = u128_to_u8(contents_dec);
-        PaddingPKSC7::with_block_size(16).remove_padding(&mut dec);
+        let mut file_enc = match File::open(&temp.0) {
+            Ok(f) => f,
+            Err(_) => return Err(BadFile),
+        };
+        let len = match file_enc.metadata() {
+            Ok(m) => m.len(),
+            Err(_) => return Err(BadFile),
+        };
+        let header = match Header::read(&mut file_enc) {
+            Ok(h) => h,
+            Err(_) => return Err(CorruptedFile),
+        };
+        let passphrase = match (&header.kdf, passphrase.filter(|p| !p.is_empty())) {
+            (Some(_), None) => return Err(PassphraseRequired),
+            (Some(_), passphrase) => passphrase,
+            (None, _) => None,
+        };
+        if let Err(_) = file_enc.seek(SeekFrom::Start(0)) {
+            return Err(BadFile);
+        }
+        // decrypted next to the destination and only moved over it once the
+        // tag checks out, so a bad download leaves an existing file alone
+        let dir = path.parent().filter(|d| !d.as_os_str().is_empty());
+        let dec = TempFile::in_dir(dir.unwrap_or(Path::new(".")));
+        let mut file_dec = match File::create(&dec.0) {
+            Ok(f) => f,
+            Err(_) => return Err(BadFile),
+        };
+        let progress = ProgressReporter::new(&window, event, Decrypting);
+        let aad = file_name.as_bytes();
+        let decrypted = match passphrase {
+            Some(p) => container::decrypt_stream_with_passphrase(
+                p.as_bytes(),
+                aad,
+                &mut file_enc,
+                &mut file_dec,
+                len,
+                &progress,
+                &op.cancel,
+            ),
+            None => container::decrypt_stream(
+                &key,
+                aad,
+                &mut file_enc,
+                &mut file_dec,
+                len,
+                &progress,
+                &op.cancel,
+            ),
+        };
+        drop(file_dec);
+        if let Err(e) = decrypted {
+            return match e.kind() {
+                ErrorKind::InvalidData | ErrorKind::UnexpectedEof => Err(CorruptedFile),
+                _ => Err(op.error(BadFile)),
+            };
+        }
+        if let Err(_) = fs::rename(&dec.0, &path) {
+            return Err(BadFile);
+        }
+        window.emit(event, Decrypting(100)).unwrap_or_default();
+        Ok(())
+    }
 
-        match fs::write(path, dec) {
-            Err(_) => Err(BadFile),
-            Ok(_) => Ok(()),
+    #[tauri::command]
+    pub async fn cancel(
+        operations: State<'_, Operations>,
+        event: &str,
+    ) -> Result<(), RequestProcessingError> {
+        match operations.cancel(event) {
+            true => Ok(()),
+            false => Err(BadRequest),
         }
     }
 
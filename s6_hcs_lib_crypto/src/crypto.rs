@@ -1,4 +1,14 @@
 pub mod asymmetric_algs;
+pub mod container;
+pub mod error;
+pub mod hash;
+pub mod kdf;
+pub mod mac;
 pub mod padding;
 pub mod prime_tests;
+pub mod progress;
+pub mod rng;
 pub mod symmetric_algs;
+
+#[cfg(test)]
+mod test_util;
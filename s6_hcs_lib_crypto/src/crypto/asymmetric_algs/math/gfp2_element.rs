@@ -1,6 +1,6 @@
+use crate::crypto::rng::SecureRng;
 use num_bigint::{BigInt, RandBigInt};
 use num_integer::Integer;
-use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use std::ops::{Add, Neg, Sub};
 
@@ -11,20 +11,18 @@ pub struct GFP2Element {
 }
 
 impl GFP2Element {
-    pub fn get_random_coefficients(prime: BigInt) -> (BigInt, BigInt) {
+    pub fn get_random_coefficients(prime: BigInt, rng: &mut dyn SecureRng) -> (BigInt, BigInt) {
         loop {
-            let a = thread_rng()
-                .gen_bigint_range(&BigInt::from(0), &BigInt::from(prime.clone().sub(1)));
-            let b = thread_rng()
-                .gen_bigint_range(&BigInt::from(0), &BigInt::from(prime.clone().sub(1)));
+            let a = rng.gen_bigint_range(&BigInt::from(0), &prime.clone().sub(1));
+            let b = rng.gen_bigint_range(&BigInt::from(0), &prime.clone().sub(1));
             if a.clone().ne(&b) {
                 return (a, b);
             }
         }
     }
 
-    pub fn new(prime: BigInt) -> Self {
-        let c = Self::get_random_coefficients(prime.clone());
+    pub fn new(prime: BigInt, rng: &mut dyn SecureRng) -> Self {
+        let c = Self::get_random_coefficients(prime.clone(), rng);
         Self {
             prime,
             coefficients: c,
@@ -22,13 +22,7 @@ impl GFP2Traces {
 
     pub fn calc_trace(&mut self, n: BigInt, c: Option<GFP2Element>) -> GFP2Element {
         if let Some(nc) = c {
-            if {
-                if let Some(sc) = self.c.clone() {
-                    sc != nc
-                } else {
-                    true
-                }
-            } {
+            if self.c.as_ref() != Some(&nc) {
                 self.c = Some(nc);
                 self.map = hashmap! {
                     BigInt::zero() => GFP2Element::new_with_val(
@@ -66,6 +60,6 @@ impl GFP2Traces {
             }
             n_curr = n_new;
         }
-        self.map.get(&n).clone().unwrap().clone()
+        self.map.get(&n).unwrap().clone()
     }
 }
@@ -1,12 +1,12 @@
 use num_bigint::{BigInt, RandBigInt};
 use num_integer::Integer;
 use num_traits::One;
-use rand::thread_rng;
 use std::ops::{Add, Sub};
 
 use crate::crypto::{
     asymmetric_algs::math::{GFP2Element, GFP2Traces},
     prime_tests::miller_rabin_test,
+    rng::SecureRng,
 };
 
 pub type PubKey = (BigInt, BigInt, GFP2Element);
@@ -22,27 +22,23 @@ impl XTRKeygen {
         Self { pub_key: None }
     }
 
-    pub fn generate_key(&mut self) -> PubKey {
-        let (ref r, ref q) = loop {
-            let r =
-                thread_rng().gen_bigint_range(&BigInt::from(u128::MIN), &BigInt::from(u128::MAX));
+    pub fn generate_key(&mut self, rng: &mut dyn SecureRng) -> PubKey {
+        let (r, q) = &loop {
+            let r = rng.gen_bigint_range(&BigInt::from(u128::MIN), &BigInt::from(u128::MAX));
             let q: BigInt = r.clone().pow(2) - r.clone() + 1;
-            if {
-                q.clone().mod_floor(&BigInt::from(12)).eq(&BigInt::from(7))
-                    && miller_rabin_test(&q, 1024)
-            } {
+            if q.clone().mod_floor(&BigInt::from(12)).eq(&BigInt::from(7))
+                && miller_rabin_test(&q, 1024, rng)
+            {
                 break (r, q);
             }
         };
 
-        let ref p = loop {
-            let k =
-                thread_rng().gen_bigint_range(&BigInt::from(u128::MIN), &BigInt::from(u128::MAX));
+        let p = &loop {
+            let k = rng.gen_bigint_range(&BigInt::from(u128::MIN), &BigInt::from(u128::MAX));
             let p: BigInt = r + k * q;
-            if {
-                p.clone().mod_floor(&BigInt::from(3)).eq(&BigInt::from(2))
-                    && miller_rabin_test(&p, 1024)
-            } {
+            if p.clone().mod_floor(&BigInt::from(3)).eq(&BigInt::from(2))
+                && miller_rabin_test(&p, 1024, rng)
+            {
                 break p;
             }
         };
@@ -52,7 +48,7 @@ impl XTRKeygen {
 
         let mut tr = GFP2Traces::new(p.clone());
         let trace = loop {
-            let c = GFP2Element::new(p.clone());
+            let c = GFP2Element::new(p.clone(), rng);
             if tr.calc_trace(p + 1, Some(c.clone())).is_p1() {
                 let trace = tr.calc_trace(quotient.clone(), None);
                 if trace != three {
@@ -65,10 +61,10 @@ impl XTRKeygen {
         self.pub_key.clone().unwrap()
     }
 
-    pub fn elgamal_key(&self) -> SecretKey {
+    pub fn elgamal_key(&self, rng: &mut dyn SecureRng) -> SecretKey {
         let pub_key = self.pub_key.clone().unwrap();
         let mut tr = GFP2Traces::new(pub_key.0.clone());
-        let k = thread_rng().gen_bigint_range(&BigInt::from(2), &pub_key.1.sub(3));
+        let k = rng.gen_bigint_range(&BigInt::from(2), &pub_key.1.sub(3));
         let c =
             GFP2Element::new_with_coefficients(pub_key.0.clone(), pub_key.2.coefficients.clone());
         let trace_gk = tr.calc_trace(k.clone(), Some(c));
@@ -80,8 +76,9 @@ impl XTRKeygen {
         q: &BigInt,
         trace: GFP2Element,
         trace_k: GFP2Element,
+        rng: &mut dyn SecureRng,
     ) -> SymmetricKey {
-        let b = thread_rng().gen_bigint_range(&BigInt::from(2), &q.sub(3));
+        let b = rng.gen_bigint_range(&BigInt::from(2), &q.sub(3));
         let mut tr = GFP2Traces::new(p.clone());
 
         let c = GFP2Element::new_with_coefficients(p.clone(), trace.coefficients.clone());
@@ -108,17 +105,20 @@ impl XTRKeygen {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::rng::seeded_rng;
 
     #[test]
     fn test_xtr() {
+        let mut rng = seeded_rng(0);
         for _ in 0..4 {
             // server (alice)
             let mut xtr = XTRKeygen::new();
-            let (p, q, trace) = xtr.generate_key();
-            let (k, trace_gk) = xtr.elgamal_key();
+            let (p, q, trace) = xtr.generate_key(&mut rng);
+            let (k, trace_gk) = xtr.elgamal_key(&mut rng);
 
             // client (bob)
-            let (ck, trace_gb) = XTRKeygen::symmetric_key(&p, &q, trace.clone(), trace_gk.clone());
+            let (ck, trace_gb) =
+                XTRKeygen::symmetric_key(&p, &q, trace.clone(), trace_gk.clone(), &mut rng);
 
             // server
             let sk = XTRKeygen::symmetric_key_recall(p.clone(), k.clone(), trace_gb.clone());
@@ -1,8 +1,15 @@
 use crate::crypto::asymmetric_algs::{math::GFP2Element, xtr::XTRKeygen};
+use crate::crypto::error::CryptoError;
+use crate::crypto::hash::SHA256;
+use crate::crypto::kdf::HKDF;
+use crate::crypto::rng::SecureRng;
 use num_bigint::BigInt;
 
 pub type PubKey = (BigInt, BigInt, GFP2Element, GFP2Element);
 
+// only keys go through the exchange, the largest cipher key is 32 bytes
+const MAX_INPUT: usize = 64;
+
 pub struct XTREncryptor {
     p: BigInt,
     q: BigInt,
@@ -14,10 +21,10 @@ pub struct XTREncryptor {
 }
 
 impl XTREncryptor {
-    pub fn new_at_server() -> Self {
+    pub fn new_at_server(rng: &mut dyn SecureRng) -> Self {
         let mut keygen = XTRKeygen::new();
-        let (p, q, trace) = keygen.generate_key();
-        let (k, trace_gk) = keygen.elgamal_key();
+        let (p, q, trace) = keygen.generate_key(rng);
+        let (k, trace_gk) = keygen.elgamal_key(rng);
         Self {
             p,
             q,
@@ -38,9 +45,10 @@ impl XTREncryptor {
         )
     }
 
-    pub fn new_at_client(pub_key: PubKey) -> Self {
+    pub fn new_at_client(pub_key: PubKey, rng: &mut dyn SecureRng) -> Self {
         let (p, q, trace, trace_gk) = pub_key;
-        let (sym_key, trace_gb) = XTRKeygen::symmetric_key(&p, &q, trace.clone(), trace_gk.clone());
+        let (sym_key, trace_gb) =
+            XTRKeygen::symmetric_key(&p, &q, trace.clone(), trace_gk.clone(), rng);
         Self {
             p,
             q,
@@ -65,47 +73,51 @@ impl XTREncryptor {
         ));
     }
 
-    pub fn encrypt(&self, input: &Vec<u8>) -> Vec<u8> {
-        let mut out = Vec::new();
-        let key = self.sym_key.clone().unwrap();
-        for i in 0..input.len() {
-            out.push(input[i] ^ (key[i % key.len()]));
+    // The shared trace is not uniform, so the pad is derived from it rather
+    // than repeated. Longer inputs are refused, the pad is one HKDF output.
+    pub fn encrypt(&self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if input.len() > MAX_INPUT {
+            return Err(CryptoError::InvalidLength);
         }
-        out
+        let secret = self.sym_key.clone().unwrap();
+        let pad = HKDF::<SHA256>::derive(&[], &secret, b"xtr key transport", input.len())?;
+        Ok(input.iter().zip(pad).map(|(x, k)| x ^ k).collect())
     }
 
-    pub fn encrypt_deal128_key(&self, key: u128) -> u128 {
-        let enc = self.encrypt(&(key.to_be_bytes().to_vec()));
-        u128::from_be_bytes(enc.try_into().unwrap())
+    pub fn encrypt_deal128_key(&self, key: u128) -> Result<u128, CryptoError> {
+        let enc = self.encrypt(&key.to_be_bytes())?;
+        Ok(u128::from_be_bytes(enc.try_into().unwrap()))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::rng::OsRng;
     use rand::random;
 
     #[test]
     fn test_xtr_wrapper_encrypt() {
-        let mut server = XTREncryptor::new_at_server();
-        let client = XTREncryptor::new_at_client(server.share_public_key_with_client());
+        let mut server = XTREncryptor::new_at_server(&mut OsRng);
+        let client = XTREncryptor::new_at_client(server.share_public_key_with_client(), &mut OsRng);
         server.derive_sym_key_at_server(client.share_trace_with_server());
 
         let m1 = vec![random(), random(), random(), random()];
-        let e = client.encrypt(&m1);
-        let m2 = server.encrypt(&e);
+        let e = client.encrypt(&m1).unwrap();
+        let m2 = server.encrypt(&e).unwrap();
         assert_eq!(m1, m2);
+        assert_eq!(client.encrypt(&[0; 65]), Err(CryptoError::InvalidLength));
     }
 
     #[test]
     fn test_xtr_wrapper_key_encrypt() {
-        let mut server = XTREncryptor::new_at_server();
-        let client = XTREncryptor::new_at_client(server.share_public_key_with_client());
+        let mut server = XTREncryptor::new_at_server(&mut OsRng);
+        let client = XTREncryptor::new_at_client(server.share_public_key_with_client(), &mut OsRng);
         server.derive_sym_key_at_server(client.share_trace_with_server());
 
         let m1 = random();
-        let e = client.encrypt_deal128_key(m1);
-        let m2 = server.encrypt_deal128_key(e);
+        let e = client.encrypt_deal128_key(m1).unwrap();
+        let m2 = server.encrypt_deal128_key(e).unwrap();
         assert_eq!(m1, m2);
     }
 }
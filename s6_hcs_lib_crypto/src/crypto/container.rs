@@ -0,0 +1,997 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::hash::SHA256;
+use crate::crypto::kdf::{scrypt, ScryptParams, HKDF};
+use crate::crypto::mac::{ct_eq, HMAC};
+use crate::crypto::padding::{
+    PaddingANSIX923, PaddingISO10126, PaddingISO7816, PaddingNone, PaddingPKSC7, PaddingZero,
+};
+use crate::crypto::progress::{CancelToken, Progress};
+use crate::crypto::rng::SecureRng;
+use crate::crypto::symmetric_algs::{
+    self, Block, BlockCipher, ChaCha20, ChaCha20Poly1305, DEALMode, LegacyRDHDecryptor, Serpent128,
+    Serpent192, Serpent256, StreamDecryptor, StreamEncryptor, TripleDES2, TripleDES3, Twofish128,
+    Twofish192, Twofish256, AES128, AES192, AES256, DEAL128, DEAL192, DEAL256, DES, DESX, GCM,
+    MARS128, MARS192, MARS256, RC6128, RC6192, RC6256,
+};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+// Layout: magic | version | cipher | mode | padding | kdf | [kdf params] |
+// iv length | iv | header mac | body | tag
+// kdf 1 is scrypt, its params are log_n | r | p | salt length | salt with r
+// and p big endian u32. The header mac is HMAC-SHA256 of everything before
+// it, keyed from the cipher key, so a passphrase header cannot be swapped
+// for a cheaper one in any mode.
+pub const MAGIC: [u8; 4] = *b"S6HC";
+
+// Files from before the container have no header, just E(iv) | E(hash ^ iv) |
+// blocks in DEAL-128 RDH with PKCS#7, see LegacyRDHDecryptor. Anything not
+// starting with MAGIC is read as one; E(iv) stands in for the iv.
+pub const LEGACY_VERSION: u8 = 0;
+pub const VERSION: u8 = 1;
+
+const KDF_NONE: u8 = 0;
+const KDF_SCRYPT: u8 = 1;
+const SALT_SIZE: usize = 16;
+const HEADER_MAC_SIZE: usize = 32;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CipherId {
+    DES = 1,
+    DEAL128,
+    DEAL192,
+    DEAL256,
+    AES128,
+    AES192,
+    AES256,
+    TripleDES2,
+    TripleDES3,
+    DESX,
+    ChaCha20,
+    Serpent128,
+    Serpent192,
+    Serpent256,
+    Twofish128,
+    Twofish192,
+    Twofish256,
+    RC6128,
+    RC6192,
+    RC6256,
+    MARS128,
+    MARS192,
+    MARS256,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ModeId {
+    ECB = 1,
+    CBC,
+    CFB,
+    OFB,
+    CTR,
+    RD,
+    RDH,
+    GCM,
+    CTS,
+    // only for ChaCha20, RFC 8439 AEAD
+    Poly1305,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PaddingId {
+    None = 0,
+    PKCS7,
+    ANSIX923,
+    ISO10126,
+    ISO7816,
+    Zero,
+}
+
+// How the key was derived from a passphrase
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyDerivation {
+    pub params: ScryptParams,
+    pub salt: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Header {
+    pub version: u8,
+    pub cipher: CipherId,
+    pub mode: ModeId,
+    pub padding: PaddingId,
+    pub kdf: Option<KeyDerivation>,
+    pub iv: Vec<u8>,
+    pub mac: Vec<u8>,
+}
+
+pub struct ContainerEncryptor {
+    header: Vec<u8>,
+    inner: Box<dyn StreamEncryptor>,
+}
+
+// The block ciphers, those with a 128-bit block and then the others. Every
+// CipherId but ChaCha20 is in one of the lists, so a new cipher is added here.
+macro_rules! block_ciphers {
+    ($m:ident!($($args:tt)*)) => {
+        $m!($($args)*
+            [
+                DEAL128, DEAL192, DEAL256, AES128, AES192, AES256, Serpent128, Serpent192,
+                Serpent256, Twofish128, Twofish192, Twofish256, RC6128, RC6192, RC6256,
+                MARS128, MARS192, MARS256
+            ],
+            [DES, TripleDES2, TripleDES3, DESX]
+        )
+    };
+}
+
+macro_rules! match_cipher {
+    (all $id:expr, $c:ident, $body:expr, $rest:expr, [$($wide:ident),*], [$($narrow:ident),*]) => {
+        match_cipher!(@match $id, $c, $body, $rest, [$($wide,)* $($narrow),*], [ChaCha20])
+    };
+    (gcm $id:expr, $c:ident, $body:expr, $rest:expr, [$($wide:ident),*], [$($narrow:ident),*]) => {
+        match_cipher!(@match $id, $c, $body, $rest, [$($wide),*], [$($narrow,)* ChaCha20])
+    };
+    (@match $id:expr, $c:ident, $body:expr, $rest:expr, [$($typed:ident),*], [$($other:ident),*]) => {
+        match $id {
+            $(CipherId::$typed => {
+                type $c = $typed;
+                $body
+            })*
+            $(CipherId::$other)|* => $rest,
+        }
+    };
+}
+
+// ChaCha20 is not a block cipher, $stream is used for it instead
+macro_rules! with_cipher {
+    ($id:expr, $c:ident => $body:expr, ChaCha20 => $stream:expr) => {
+        block_ciphers!(match_cipher!(all $id, $c, $body, $stream,))
+    };
+}
+
+// same as with_cipher, limited to ciphers with a 128-bit block
+macro_rules! with_gcm_cipher {
+    ($id:expr, $c:ident => $body:expr) => {
+        block_ciphers!(match_cipher!(gcm $id, $c, $body, Err(CryptoError::Unsupported),))
+    };
+}
+
+macro_rules! with_padding {
+    ($id:expr, $p:ident => $body:expr) => {
+        match $id {
+            PaddingId::None => {
+                type $p = PaddingNone;
+                $body
+            }
+            PaddingId::PKCS7 => {
+                type $p = PaddingPKSC7;
+                $body
+            }
+            PaddingId::ANSIX923 => {
+                type $p = PaddingANSIX923;
+                $body
+            }
+            PaddingId::ISO10126 => {
+                type $p = PaddingISO10126;
+                $body
+            }
+            PaddingId::ISO7816 => {
+                type $p = PaddingISO7816;
+                $body
+            }
+            PaddingId::Zero => {
+                type $p = PaddingZero;
+                $body
+            }
+        }
+    };
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_key<C: BlockCipher>(key: &[u8]) -> Result<C::Key, CryptoError> {
+    if key.len() != C::Key::BYTES {
+        return Err(CryptoError::InvalidLength);
+    }
+    Ok(C::Key::from_bytes(key))
+}
+
+impl CipherId {
+    const ALL: [Self; 23] = [
+        Self::DES,
+        Self::DEAL128,
+        Self::DEAL192,
+        Self::DEAL256,
+        Self::AES128,
+        Self::AES192,
+        Self::AES256,
+        Self::TripleDES2,
+        Self::TripleDES3,
+        Self::DESX,
+        Self::ChaCha20,
+        Self::Serpent128,
+        Self::Serpent192,
+        Self::Serpent256,
+        Self::Twofish128,
+        Self::Twofish192,
+        Self::Twofish256,
+        Self::RC6128,
+        Self::RC6192,
+        Self::RC6256,
+        Self::MARS128,
+        Self::MARS192,
+        Self::MARS256,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| *c as u8 == id)
+    }
+
+    pub fn key_size(self) -> usize {
+        with_cipher!(self, C => <C as BlockCipher>::Key::BYTES, ChaCha20 => {
+            ChaCha20Poly1305::KEY_SIZE
+        })
+    }
+
+    pub fn generate_key(self, rng: &mut dyn SecureRng) -> Vec<u8> {
+        with_cipher!(self, C => C::generate_key(rng).to_bytes(), ChaCha20 => {
+            ChaCha20Poly1305::generate_key(rng).to_vec()
+        })
+    }
+
+    pub fn block_size(self) -> usize {
+        with_cipher!(self, C => <C as BlockCipher>::Block::BYTES, ChaCha20 => ChaCha20::BLOCK_SIZE)
+    }
+}
+
+impl ModeId {
+    const ALL: [Self; 10] = [
+        Self::ECB,
+        Self::CBC,
+        Self::CFB,
+        Self::OFB,
+        Self::CTR,
+        Self::RD,
+        Self::RDH,
+        Self::GCM,
+        Self::CTS,
+        Self::Poly1305,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| *m as u8 == id)
+    }
+
+    fn block_mode(self) -> Option<DEALMode> {
+        match self {
+            Self::ECB => Some(DEALMode::ECB),
+            Self::CBC => Some(DEALMode::CBC),
+            Self::CFB => Some(DEALMode::CFB),
+            Self::OFB => Some(DEALMode::OFB),
+            Self::CTR => Some(DEALMode::CTR),
+            Self::RD => Some(DEALMode::RD),
+            Self::RDH => Some(DEALMode::RDH),
+            Self::CTS => Some(DEALMode::CTS),
+            Self::GCM | Self::Poly1305 => None,
+        }
+    }
+}
+
+impl PaddingId {
+    const ALL: [Self; 6] = [
+        Self::None,
+        Self::PKCS7,
+        Self::ANSIX923,
+        Self::ISO10126,
+        Self::ISO7816,
+        Self::Zero,
+    ];
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| *p as u8 == id)
+    }
+}
+
+impl KeyDerivation {
+    pub fn derive_key(&self, passphrase: &[u8], cipher: CipherId) -> Result<Vec<u8>, CryptoError> {
+        scrypt(passphrase, &self.salt, self.params, cipher.key_size())
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let params = self.params;
+        [
+            &[params.log_n][..],
+            &params.r.to_be_bytes(),
+            &params.p.to_be_bytes(),
+            &[self.salt.len() as u8],
+            &self.salt,
+        ]
+        .concat()
+    }
+
+    fn read(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut fixed = [0u8; 10];
+        reader.read_exact(&mut fixed)?;
+        let r = u32::from_be_bytes(fixed[1..5].try_into().unwrap());
+        let p = u32::from_be_bytes(fixed[5..9].try_into().unwrap());
+        // checked here, before a hostile header can make scrypt allocate
+        let params =
+            ScryptParams::new(fixed[0], r, p).map_err(|_| invalid("unsupported kdf cost"))?;
+        let mut salt = vec![0; fixed[9] as usize];
+        reader.read_exact(&mut salt)?;
+        Ok(Self { params, salt })
+    }
+}
+
+impl Header {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.version == LEGACY_VERSION {
+            return self.iv.clone();
+        }
+        [self.fields(), self.mac.clone()].concat()
+    }
+
+    // Everything the header mac and the AEAD modes authenticate
+    fn fields(&self) -> Vec<u8> {
+        let kdf = match &self.kdf {
+            None => vec![KDF_NONE],
+            Some(kdf) => [vec![KDF_SCRYPT], kdf.to_bytes()].concat(),
+        };
+        let fields = [
+            self.version,
+            self.cipher as u8,
+            self.mode as u8,
+            self.padding as u8,
+        ];
+        [&MAGIC[..], &fields, &kdf, &[self.iv.len() as u8], &self.iv].concat()
+    }
+
+    fn compute_mac(&self, key: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mac_key = HKDF::<SHA256>::derive(&[], key, b"S6HC header mac", HEADER_MAC_SIZE)?;
+        Ok(HMAC::<SHA256>::mac(&mac_key, &self.fields()))
+    }
+
+    // Fails for a wrong key as well as for a changed header
+    pub fn verify(&self, key: &[u8]) -> Result<(), CryptoError> {
+        if self.version == LEGACY_VERSION {
+            return Ok(());
+        }
+        match ct_eq(&self.compute_mac(key)?, &self.mac) {
+            true => Ok(()),
+            false => Err(CryptoError::AuthenticationFailed),
+        }
+    }
+
+    pub fn read(reader: &mut dyn Read) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            let mut iv = [0u8; 16];
+            iv[..4].copy_from_slice(&magic);
+            reader.read_exact(&mut iv[4..])?;
+            return Ok(Self {
+                version: LEGACY_VERSION,
+                cipher: CipherId::DEAL128,
+                mode: ModeId::RDH,
+                padding: PaddingId::PKCS7,
+                kdf: None,
+                iv: iv.to_vec(),
+                mac: Vec::new(),
+            });
+        }
+        let mut fixed = [0u8; 5];
+        reader.read_exact(&mut fixed)?;
+        let version = fixed[0];
+        if version != VERSION {
+            return Err(invalid("unsupported container version"));
+        }
+        let kdf = match fixed[4] {
+            KDF_NONE => None,
+            KDF_SCRYPT => Some(KeyDerivation::read(reader)?),
+            _ => return Err(invalid("unknown kdf")),
+        };
+        let cipher = CipherId::from_id(fixed[1]).ok_or_else(|| invalid("unknown cipher"))?;
+        let mode = ModeId::from_id(fixed[2]).ok_or_else(|| invalid("unknown mode"))?;
+        let padding = PaddingId::from_id(fixed[3]).ok_or_else(|| invalid("unknown padding"))?;
+        let mut iv_len = [0u8];
+        reader.read_exact(&mut iv_len)?;
+        let mut iv = vec![0; iv_len[0] as usize];
+        reader.read_exact(&mut iv)?;
+        let mut mac = vec![0; HEADER_MAC_SIZE];
+        reader.read_exact(&mut mac)?;
+        Ok(Self {
+            version,
+            cipher,
+            mode,
+            padding,
+            kdf,
+            iv,
+            mac,
+        })
+    }
+}
+
+impl StreamEncryptor for ContainerEncryptor {
+    fn iv(&self) -> Vec<u8> {
+        self.header.clone()
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.inner.update(input)
+    }
+
+    fn finalize(&mut self) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        self.inner.finalize()
+    }
+}
+
+fn block_encryptor<C: BlockCipher + 'static>(
+    mode: DEALMode,
+    padding: PaddingId,
+    key: &[u8],
+    rng: &mut dyn SecureRng,
+) -> Result<Box<dyn StreamEncryptor>, CryptoError> {
+    let key = parse_key::<C>(key)?;
+    if padding == PaddingId::None && mode.needs_padding() {
+        return Err(CryptoError::Unsupported);
+    }
+    with_padding!(padding, P => Ok(Box::new(mode.encryptor::<C, P>(key, rng))))
+}
+
+fn block_decryptor<C: BlockCipher + 'static>(
+    mode: DEALMode,
+    padding: PaddingId,
+    key: &[u8],
+    iv: &[u8],
+) -> Result<Box<dyn StreamDecryptor>, CryptoError> {
+    let key = parse_key::<C>(key)?;
+    if padding == PaddingId::None && mode.needs_padding() {
+        return Err(CryptoError::Unsupported);
+    }
+    with_padding!(padding, P => Ok(Box::new(mode.decryptor::<C, P>(key, iv)?)))
+}
+
+// The header is authenticated along with the aad, so ids cannot be swapped
+fn gcm_encryptor<C: BlockCipher<Block = u128> + 'static>(
+    header: &Header,
+    key: &[u8],
+    aad: &[u8],
+) -> Result<Box<dyn StreamEncryptor>, CryptoError> {
+    let gcm = GCM::<C>::with_key(parse_key::<C>(key)?);
+    let aad = [header.fields(), aad.to_vec()].concat();
+    Ok(Box::new(gcm.encryptor(&header.iv, &aad)))
+}
+
+fn gcm_decryptor<C: BlockCipher<Block = u128> + 'static>(
+    header: &Header,
+    key: &[u8],
+    aad: &[u8],
+) -> Result<Box<dyn StreamDecryptor>, CryptoError> {
+    let gcm = GCM::<C>::with_key(parse_key::<C>(key)?);
+    let aad = [header.fields(), aad.to_vec()].concat();
+    Ok(Box::new(gcm.decryptor(&header.iv, &aad)))
+}
+
+fn chacha_key(key: &[u8]) -> Result<ChaCha20Poly1305, CryptoError> {
+    let key = key.try_into().map_err(|_| CryptoError::InvalidLength)?;
+    Ok(ChaCha20Poly1305::with_key(key))
+}
+
+// The AEAD modes, GCM over a 128-bit block cipher or ChaCha20 with Poly1305
+fn aead_encryptor(
+    header: &Header,
+    key: &[u8],
+    aad: &[u8],
+) -> Result<Box<dyn StreamEncryptor>, CryptoError> {
+    match (header.cipher, header.mode) {
+        (CipherId::ChaCha20, ModeId::Poly1305) => {
+            let aad = [header.fields(), aad.to_vec()].concat();
+            Ok(Box::new(chacha_key(key)?.encryptor(&header.iv, &aad)?))
+        }
+        (CipherId::ChaCha20, _) | (_, ModeId::Poly1305) => Err(CryptoError::Unsupported),
+        (cipher, _) => with_gcm_cipher!(cipher, C => gcm_encryptor::<C>(header, key, aad)),
+    }
+}
+
+fn aead_decryptor(
+    header: &Header,
+    key: &[u8],
+    aad: &[u8],
+) -> Result<Box<dyn StreamDecryptor>, CryptoError> {
+    match (header.cipher, header.mode) {
+        (CipherId::ChaCha20, ModeId::Poly1305) => {
+            let aad = [header.fields(), aad.to_vec()].concat();
+            Ok(Box::new(chacha_key(key)?.decryptor(&header.iv, &aad)?))
+        }
+        (CipherId::ChaCha20, _) | (_, ModeId::Poly1305) => Err(CryptoError::Unsupported),
+        (cipher, _) => with_gcm_cipher!(cipher, C => gcm_decryptor::<C>(header, key, aad)),
+    }
+}
+
+// aad is only authenticated by the AEAD modes, the other modes ignore it
+pub fn encryptor(
+    cipher: CipherId,
+    mode: ModeId,
+    padding: PaddingId,
+    key: &[u8],
+    aad: &[u8],
+    rng: &mut dyn SecureRng,
+) -> Result<ContainerEncryptor, CryptoError> {
+    build_encryptor(cipher, mode, padding, None, key, aad, rng)
+}
+
+// Derives the key from the passphrase with a fresh salt. The header keeps
+// the salt and cost, so decrypt_stream_with_passphrase needs nothing else.
+pub fn passphrase_encryptor(
+    cipher: CipherId,
+    mode: ModeId,
+    padding: PaddingId,
+    passphrase: &[u8],
+    params: ScryptParams,
+    aad: &[u8],
+    rng: &mut dyn SecureRng,
+) -> Result<ContainerEncryptor, CryptoError> {
+    let mut salt = vec![0; SALT_SIZE];
+    rng.fill_bytes(&mut salt);
+    let kdf = KeyDerivation { params, salt };
+    let key = kdf.derive_key(passphrase, cipher)?;
+    build_encryptor(cipher, mode, padding, Some(kdf), &key, aad, rng)
+}
+
+fn build_encryptor(
+    cipher: CipherId,
+    mode: ModeId,
+    padding: PaddingId,
+    kdf: Option<KeyDerivation>,
+    key: &[u8],
+    aad: &[u8],
+    rng: &mut dyn SecureRng,
+) -> Result<ContainerEncryptor, CryptoError> {
+    let (mut header, inner) = match mode.block_mode() {
+        Some(block_mode) => {
+            let inner = with_cipher!(cipher, C => {
+                block_encryptor::<C>(block_mode, padding, key, rng)
+            }, ChaCha20 => Err(CryptoError::Unsupported))?;
+            let iv = inner.iv();
+            let header = Header {
+                version: VERSION,
+                cipher,
+                mode,
+                padding,
+                kdf,
+                iv,
+                mac: Vec::new(),
+            };
+            (header, inner)
+        }
+        None => {
+            if padding != PaddingId::None {
+                return Err(CryptoError::Unsupported);
+            }
+            let header = Header {
+                version: VERSION,
+                cipher,
+                mode,
+                padding,
+                kdf,
+                iv: match mode {
+                    ModeId::Poly1305 => ChaCha20Poly1305::generate_iv(rng),
+                    _ => GCM::<AES128>::generate_iv(rng),
+                },
+                mac: Vec::new(),
+            };
+            let inner = aead_encryptor(&header, key, aad)?;
+            (header, inner)
+        }
+    };
+    header.mac = header.compute_mac(key)?;
+    Ok(ContainerEncryptor {
+        header: header.to_bytes(),
+        inner,
+    })
+}
+
+// The legacy format needs the length up front, see decrypt_with
+pub fn decryptor(
+    header: &Header,
+    key: &[u8],
+    aad: &[u8],
+) -> Result<Box<dyn StreamDecryptor>, CryptoError> {
+    if header.version == LEGACY_VERSION {
+        return Err(CryptoError::Unsupported);
+    }
+    header.verify(key)?;
+    match header.mode.block_mode() {
+        Some(mode) => with_cipher!(header.cipher, C => {
+            block_decryptor::<C>(mode, header.padding, key, &header.iv)
+        }, ChaCha20 => Err(CryptoError::Unsupported)),
+        None => {
+            if header.padding != PaddingId::None {
+                return Err(CryptoError::Unsupported);
+            }
+            aead_decryptor(header, key, aad)
+        }
+    }
+}
+
+// len is what follows the iv, the hash block and then the data blocks
+fn legacy_decryptor(
+    key: &[u8],
+    iv: &[u8],
+    len: u64,
+) -> Result<Box<dyn StreamDecryptor>, CryptoError> {
+    let key = parse_key::<DEAL128>(key)?;
+    let blocks = (len / 16).saturating_sub(1) as usize;
+    Ok(Box::new(LegacyRDHDecryptor::new(key, iv, blocks)?))
+}
+
+// Reads the header, picks the decryptor it describes and decrypts the rest.
+// len is the size of the whole container, only used to report progress.
+pub fn decrypt_stream(
+    key: &[u8],
+    aad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    len: u64,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> io::Result<Header> {
+    let key = |header: &Header| match header.kdf {
+        None => Ok(key.to_vec()),
+        Some(_) => Err(invalid("container needs a passphrase")),
+    };
+    decrypt_with(key, aad, reader, writer, len, progress, cancel)
+}
+
+pub fn decrypt_stream_with_passphrase(
+    passphrase: &[u8],
+    aad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    len: u64,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> io::Result<Header> {
+    let key = |header: &Header| match &header.kdf {
+        None => Err(invalid("container has no passphrase")),
+        Some(kdf) => kdf
+            .derive_key(passphrase, header.cipher)
+            .map_err(|_| invalid("unsupported kdf cost")),
+    };
+    decrypt_with(key, aad, reader, writer, len, progress, cancel)
+}
+
+fn decrypt_with(
+    key: impl FnOnce(&Header) -> io::Result<Vec<u8>>,
+    aad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    len: u64,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> io::Result<Header> {
+    let header = Header::read(reader)?;
+    let key = key(&header)?;
+    let len = len.saturating_sub(header.to_bytes().len() as u64);
+    let mut decryptor = match header.version {
+        LEGACY_VERSION => legacy_decryptor(&key, &header.iv, len),
+        _ => decryptor(&header, &key, aad),
+    }
+    .map_err(|e| match e {
+        CryptoError::AuthenticationFailed => invalid("wrong key or corrupted header"),
+        _ => invalid("unsupported container parameters"),
+    })?;
+    symmetric_algs::decrypt_stream(&mut *decryptor, reader, writer, len, progress, cancel)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::symmetric_algs::encrypt_stream;
+    use crate::crypto::test_util::legacy_rdh;
+    use rand::random;
+
+    fn roundtrip(cipher: CipherId, mode: ModeId, padding: PaddingId, data: &[u8]) {
+        let key = cipher.generate_key(&mut OsRng);
+        let mut enc = Vec::new();
+        let mut encryptor = encryptor(cipher, mode, padding, &key, b"name", &mut OsRng).unwrap();
+        let len = data.len() as u64;
+        encrypt_stream(
+            &mut encryptor,
+            &mut &data[..],
+            &mut enc,
+            len,
+            &(),
+            &CancelToken::new(),
+        )
+        .unwrap();
+
+        let mut dec = Vec::new();
+        let len = enc.len() as u64;
+        let cancel = CancelToken::new();
+        let header = decrypt_stream(&key, b"name", &mut &enc[..], &mut dec, len, &(), &cancel);
+        let header = header.unwrap();
+        assert_eq!(
+            (header.cipher, header.mode, header.padding),
+            (cipher, mode, padding)
+        );
+        assert_eq!(dec, data);
+    }
+
+    #[test]
+    fn test_container() {
+        let data: Vec<u8> = (0..100).map(|_| random()).collect();
+        for cipher in CipherId::ALL {
+            for mode in ModeId::ALL {
+                match (cipher, mode) {
+                    (CipherId::ChaCha20, ModeId::Poly1305) => {
+                        roundtrip(cipher, mode, PaddingId::None, &data)
+                    }
+                    (CipherId::ChaCha20, _) | (_, ModeId::Poly1305) => {
+                        let key = vec![0; cipher.key_size()];
+                        let padding = PaddingId::None;
+                        assert!(encryptor(cipher, mode, padding, &key, &[], &mut OsRng).is_err());
+                    }
+                    (_, ModeId::GCM) if cipher.block_size() == 16 => {
+                        roundtrip(cipher, mode, PaddingId::None, &data)
+                    }
+                    (_, ModeId::GCM) => {}
+                    _ => roundtrip(cipher, mode, PaddingId::PKCS7, &data),
+                }
+            }
+        }
+        for len in [0, 1, 63, 64, 65, 100] {
+            let (cipher, mode) = (CipherId::ChaCha20, ModeId::Poly1305);
+            roundtrip(cipher, mode, PaddingId::None, &data[..len]);
+        }
+        for padding in PaddingId::ALL.into_iter().skip(1) {
+            roundtrip(CipherId::AES128, ModeId::CBC, padding, &[0x5a; 37]);
+        }
+        for mode in [ModeId::CFB, ModeId::OFB, ModeId::CTR, ModeId::CTS] {
+            for len in [0, 1, 15, 16, 17, 32, 100] {
+                roundtrip(CipherId::AES128, mode, PaddingId::None, &data[..len]);
+            }
+        }
+        assert!(encryptor(
+            CipherId::AES128,
+            ModeId::CBC,
+            PaddingId::None,
+            &[0; 16],
+            &[],
+            &mut OsRng
+        )
+        .is_err());
+        assert!(encryptor(
+            CipherId::DES,
+            ModeId::GCM,
+            PaddingId::None,
+            &[0; 8],
+            &[],
+            &mut OsRng
+        )
+        .is_err());
+        assert!(encryptor(
+            CipherId::AES128,
+            ModeId::CBC,
+            PaddingId::PKCS7,
+            &[0; 8],
+            &[],
+            &mut OsRng
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_header() {
+        let cipher = CipherId::DEAL128;
+        let key = cipher.generate_key(&mut OsRng);
+        let mut enc = Vec::new();
+        let encryptor = encryptor(cipher, ModeId::GCM, PaddingId::None, &key, &[], &mut OsRng);
+        let mut encryptor = encryptor.unwrap();
+        let cancel = CancelToken::new();
+        encrypt_stream(
+            &mut encryptor,
+            &mut &[7u8; 40][..],
+            &mut enc,
+            40,
+            &(),
+            &cancel,
+        )
+        .unwrap();
+
+        let header = Header::read(&mut &enc[..]).unwrap();
+        assert_eq!(header.iv.len(), 12);
+        assert_eq!(Header::read(&mut &header.to_bytes()[..]).unwrap(), header);
+
+        // magic, version, mode id, kdf id, iv, header mac and tag
+        for i in [0, 4, 6, 8, 10, 30, enc.len() - 1] {
+            let mut bad = enc.clone();
+            bad[i] ^= 1;
+            let len = bad.len() as u64;
+            let res = decrypt_stream(&key, &[], &mut &bad[..], &mut Vec::new(), len, &(), &cancel);
+            assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+
+        // the header mac covers the modes without a tag too
+        let mut enc = Vec::new();
+        let cbc = super::encryptor(cipher, ModeId::CBC, PaddingId::PKCS7, &key, &[], &mut OsRng);
+        encrypt_stream(
+            &mut cbc.unwrap(),
+            &mut &[7u8; 40][..],
+            &mut enc,
+            40,
+            &(),
+            &cancel,
+        )
+        .unwrap();
+        let mut bad = enc.clone();
+        bad[7] = PaddingId::ISO10126 as u8;
+        let len = bad.len() as u64;
+        let res = decrypt_stream(&key, &[], &mut &bad[..], &mut Vec::new(), len, &(), &cancel);
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let res = decrypt_stream(
+            &[0; 16],
+            &[],
+            &mut &enc[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_legacy() {
+        let key: u128 = random();
+        let data: Vec<u8> = (0..100).map(|_| random()).collect();
+        let enc = legacy_rdh(key, random::<u128>() >> 1, &data);
+        let header = Header::read(&mut &enc[..]).unwrap();
+        assert_eq!(header.version, LEGACY_VERSION);
+        assert_eq!(header.to_bytes(), enc[..16]);
+
+        let key = key.to_be_bytes();
+        let len = enc.len() as u64;
+        let cancel = CancelToken::new();
+        let mut dec = Vec::new();
+        decrypt_stream(&key, b"name", &mut &enc[..], &mut dec, len, &(), &cancel).unwrap();
+        assert_eq!(dec, data);
+
+        // the block count is hashed, so the length has to be right
+        let res = decrypt_stream(
+            &key,
+            &[],
+            &mut &enc[..],
+            &mut Vec::new(),
+            len - 16,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let params = ScryptParams::new(4, 1, 1).unwrap();
+        let cipher = CipherId::AES256;
+        let mut enc = Vec::new();
+        let passphrase = passphrase_encryptor(
+            cipher,
+            ModeId::GCM,
+            PaddingId::None,
+            b"hunter2",
+            params,
+            b"name",
+            &mut OsRng,
+        );
+        let cancel = CancelToken::new();
+        let data = [3u8; 50];
+        encrypt_stream(
+            &mut passphrase.unwrap(),
+            &mut &data[..],
+            &mut enc,
+            50,
+            &(),
+            &cancel,
+        )
+        .unwrap();
+
+        let header = Header::read(&mut &enc[..]).unwrap();
+        let kdf = header.kdf.clone().unwrap();
+        assert_eq!((kdf.params, kdf.salt.len()), (params, SALT_SIZE));
+        assert_eq!(Header::read(&mut &header.to_bytes()[..]).unwrap(), header);
+
+        let len = enc.len() as u64;
+        let mut dec = Vec::new();
+        let res = decrypt_stream_with_passphrase(
+            b"hunter2",
+            b"name",
+            &mut &enc[..],
+            &mut dec,
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap(), header);
+        assert_eq!(dec, data);
+
+        let res = decrypt_stream_with_passphrase(
+            b"hunter3",
+            b"name",
+            &mut &enc[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // a raw key does not open it, nor does a passphrase open a keyed one
+        let key = kdf.derive_key(b"hunter2", cipher).unwrap();
+        let res = decrypt_stream(
+            &key,
+            b"name",
+            &mut &enc[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut keyed = Vec::new();
+        let encryptor = encryptor(cipher, ModeId::GCM, PaddingId::None, &key, &[], &mut OsRng);
+        encrypt_stream(
+            &mut encryptor.unwrap(),
+            &mut &data[..],
+            &mut keyed,
+            50,
+            &(),
+            &cancel,
+        )
+        .unwrap();
+        let len = keyed.len() as u64;
+        let res = decrypt_stream_with_passphrase(
+            b"hunter2",
+            &[],
+            &mut &keyed[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // the cost is checked before deriving
+        let mut costly = enc.clone();
+        costly[9] = 40;
+        let res = decrypt_stream_with_passphrase(
+            b"hunter2",
+            b"name",
+            &mut &costly[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // nor can the cost be lowered, the header mac no longer matches
+        let mut cheap = enc.clone();
+        cheap[9] = 3;
+        let res = decrypt_stream_with_passphrase(
+            b"hunter2",
+            b"name",
+            &mut &cheap[..],
+            &mut Vec::new(),
+            len,
+            &(),
+            &cancel,
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::crypto::padding::PaddingError;
+use std::{fmt, io};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CryptoError {
+    // stopped through its CancelToken
+    Cancelled,
+    // a key, iv, tag or input of a length the algorithm does not take
+    InvalidLength,
+    // the padding did not check out after decryption
+    InvalidPadding,
+    // tag or hash mismatch, the key is wrong or the data was changed
+    AuthenticationFailed,
+    // more data than the algorithm can take under one key and iv
+    LimitExceeded,
+    // cost or other parameters out of the supported range
+    InvalidParameters,
+    // a key the algorithm refuses, such as equal XTS data and tweak keys
+    InvalidKey,
+    // a combination of cipher, mode and padding that does not exist
+    Unsupported,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            Self::Cancelled => "cancelled",
+            Self::InvalidLength => "invalid length",
+            Self::InvalidPadding => "invalid padding",
+            Self::AuthenticationFailed => "authentication failed",
+            Self::LimitExceeded => "too much data for one key and iv",
+            Self::InvalidParameters => "unsupported parameters",
+            Self::InvalidKey => "invalid key",
+            Self::Unsupported => "unsupported combination of algorithms",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+impl From<PaddingError> for CryptoError {
+    fn from(e: PaddingError) -> Self {
+        match e {
+            PaddingError::InvalidLength => Self::InvalidLength,
+            PaddingError::InvalidPadding => Self::InvalidPadding,
+        }
+    }
+}
+
+// cancellation is not a problem with the data
+impl From<CryptoError> for io::Error {
+    fn from(e: CryptoError) -> Self {
+        match e {
+            CryptoError::Cancelled => io::Error::other(e),
+            _ => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
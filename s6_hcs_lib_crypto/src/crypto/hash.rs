@@ -0,0 +1,8 @@
+mod base;
+mod sha2;
+mod sha3;
+
+pub(crate) use base::absorb;
+pub use base::Digest;
+pub use sha2::{SHA256, SHA512};
+pub use sha3::{SHA3, SHA3_256, SHA3_512};
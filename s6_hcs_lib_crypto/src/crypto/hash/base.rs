@@ -0,0 +1,41 @@
+pub trait Digest: Clone + Default + Send + Sync {
+    const OUTPUT_SIZE: usize;
+    // input block size in bytes, HMAC pads keys to it
+    const BLOCK_SIZE: usize;
+
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn update(&mut self, data: &[u8]);
+    fn finalize(self) -> Vec<u8>;
+
+    fn digest(data: &[u8]) -> Vec<u8> {
+        let mut hasher = Self::new();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+// Passes whole blocks of buffered and new data to compress and keeps the rest
+pub fn absorb(
+    buffer: &mut Vec<u8>,
+    block_size: usize,
+    data: &[u8],
+    mut compress: impl FnMut(&[u8]),
+) {
+    let mut data = data;
+    if !buffer.is_empty() {
+        let take = data.len().min(block_size - buffer.len());
+        buffer.extend_from_slice(&data[..take]);
+        data = &data[take..];
+        if buffer.len() < block_size {
+            return;
+        }
+        compress(buffer);
+        buffer.clear();
+    }
+    let mut blocks = data.chunks_exact(block_size);
+    blocks.by_ref().for_each(&mut compress);
+    buffer.extend_from_slice(blocks.remainder());
+}
@@ -0,0 +1,166 @@
+use crate::crypto::hash::base::{absorb, Digest};
+
+// FIPS 202
+const ROUND_CONSTANTS: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808a,
+    0x8000000080008000,
+    0x000000000000808b,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008a,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000a,
+    0x000000008000808b,
+    0x800000000000008b,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800a,
+    0x800000008000000a,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+// rotation of lane x + 5y
+const RHO: [u32; 25] = [
+    0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+fn keccak_f(a: &mut [u64; 25]) {
+    for rc in ROUND_CONSTANTS {
+        // theta
+        let c: [u64; 5] =
+            std::array::from_fn(|x| a[x] ^ a[x + 5] ^ a[x + 10] ^ a[x + 15] ^ a[x + 20]);
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                a[x + 5 * y] ^= d;
+            }
+        }
+
+        // rho and pi, lane (x, y) moves to (y, 2x + 3y)
+        let mut b = [0u64; 25];
+        for x in 0..5 {
+            for y in 0..5 {
+                b[y + 5 * ((2 * x + 3 * y) % 5)] = a[x + 5 * y].rotate_left(RHO[x + 5 * y]);
+            }
+        }
+
+        // chi
+        for y in 0..5 {
+            for x in 0..5 {
+                a[x + 5 * y] = b[x + 5 * y] ^ (!b[(x + 1) % 5 + 5 * y] & b[(x + 2) % 5 + 5 * y]);
+            }
+        }
+
+        // iota
+        a[0] ^= rc;
+    }
+}
+
+// Keccak sponge with the SHA-3 domain bits. RATE is in bytes, the
+// capacity is twice the output size.
+#[derive(Clone, Debug)]
+pub struct SHA3<const RATE: usize, const OUTPUT: usize> {
+    state: [u64; 25],
+    buffer: Vec<u8>,
+}
+
+pub type SHA3_256 = SHA3<136, 32>;
+pub type SHA3_512 = SHA3<72, 64>;
+
+impl<const RATE: usize, const OUTPUT: usize> Default for SHA3<RATE, OUTPUT> {
+    fn default() -> Self {
+        Self {
+            state: [0; 25],
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<const RATE: usize, const OUTPUT: usize> SHA3<RATE, OUTPUT> {
+    fn absorb_block(state: &mut [u64; 25], block: &[u8]) {
+        for (lane, chunk) in state.iter_mut().zip(block.chunks(8)) {
+            *lane ^= u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        keccak_f(state);
+    }
+}
+
+impl<const RATE: usize, const OUTPUT: usize> Digest for SHA3<RATE, OUTPUT> {
+    const OUTPUT_SIZE: usize = OUTPUT;
+    const BLOCK_SIZE: usize = RATE;
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        absorb(&mut self.buffer, RATE, data, |block| {
+            Self::absorb_block(state, block)
+        });
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        // domain bits 01, then pad10*1. The output fits in one block.
+        let mut block = std::mem::take(&mut self.buffer);
+        let len = block.len();
+        block.resize(RATE, 0);
+        block[len] ^= 0x06;
+        block[RATE - 1] ^= 0x80;
+        Self::absorb_block(&mut self.state, &block);
+        self.state
+            .iter()
+            .flat_map(|lane| lane.to_le_bytes())
+            .take(OUTPUT)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_sha3() {
+        // FIPS 202 examples
+        assert_eq!(
+            SHA3_256::digest(b""),
+            hex("a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a")
+        );
+        assert_eq!(
+            SHA3_256::digest(b"abc"),
+            hex("3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532")
+        );
+        assert_eq!(
+            SHA3_512::digest(b"abc"),
+            hex(concat!(
+                "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e",
+                "10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+            ))
+        );
+
+        // 1600 bits of 0xa3 cross the rate of both
+        let data = [0xa3; 200];
+        assert_eq!(
+            SHA3_256::digest(&data),
+            hex("79f38adec5c20307a98ef76e8324afbfd46cfd81b22e3973c65fa1bd9de31787")
+        );
+        let mut hasher = SHA3_512::new();
+        for chunk in data.chunks(7) {
+            hasher.update(chunk);
+        }
+        assert_eq!(
+            hasher.finalize(),
+            hex(concat!(
+                "e76dfad22084a8b1467fcf2ffa58361bec7628edf5f3fdc0e4805dc48caeeca8",
+                "1b7c13c30adf52a3659584739a2df46be589c51ca1a4a8416df6545a1ce8ba00"
+            ))
+        );
+    }
+}
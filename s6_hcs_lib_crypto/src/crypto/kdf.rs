@@ -0,0 +1,7 @@
+mod hkdf;
+mod pbkdf2;
+mod scrypt;
+
+pub use hkdf::HKDF;
+pub use pbkdf2::pbkdf2;
+pub use scrypt::{scrypt, ScryptParams};
@@ -0,0 +1,95 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::hash::Digest;
+use crate::crypto::mac::{Mac, HMAC};
+use std::marker::PhantomData;
+
+// RFC 5869. For secrets that already have enough entropy, such as key
+// exchange results; passwords go through pbkdf2 instead.
+#[derive(Clone)]
+pub struct HKDF<D: Digest> {
+    prk: Vec<u8>,
+    _digest: PhantomData<D>,
+}
+
+impl<D: Digest> HKDF<D> {
+    // an empty salt stands for a block of zeros
+    pub fn extract(salt: &[u8], ikm: &[u8]) -> Self {
+        let salt = match salt.is_empty() {
+            true => vec![0; D::OUTPUT_SIZE],
+            false => salt.to_vec(),
+        };
+        Self {
+            prk: HMAC::<D>::mac(&salt, ikm),
+            _digest: PhantomData,
+        }
+    }
+
+    // len is at most 255 digests
+    pub fn expand(&self, info: &[u8], len: usize) -> Result<Vec<u8>, CryptoError> {
+        if len > 255 * D::OUTPUT_SIZE {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mac = HMAC::<D>::with_key(&self.prk);
+        let mut out = Vec::with_capacity(len);
+        let mut t = Vec::new();
+        for i in 1..=len.div_ceil(D::OUTPUT_SIZE) as u8 {
+            let mut mac = mac.clone();
+            mac.update(&t);
+            mac.update(info);
+            mac.update(&[i]);
+            t = mac.finalize();
+            out.extend_from_slice(&t);
+        }
+        out.truncate(len);
+        Ok(out)
+    }
+
+    pub fn derive(
+        salt: &[u8],
+        ikm: &[u8],
+        info: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, CryptoError> {
+        Self::extract(salt, ikm).expand(info, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::SHA256;
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_hkdf() {
+        // RFC 5869 test cases 1 and 3
+        let ikm = [0x0b; 22];
+        let salt: Vec<u8> = (0..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+        let hkdf = HKDF::<SHA256>::extract(&salt, &ikm);
+        assert_eq!(
+            hkdf.prk,
+            hex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+        assert_eq!(
+            hkdf.expand(&info, 42),
+            Ok(hex(concat!(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf",
+                "34007208d5b887185865"
+            )))
+        );
+        assert_eq!(
+            HKDF::<SHA256>::derive(&[], &ikm, &[], 42),
+            Ok(hex(concat!(
+                "8da4e775a563c18f715f802a063c5a31b8a11f5c5ee1879ec3454e5f3c738d2d",
+                "9d201395faa4b61a96c8"
+            )))
+        );
+
+        assert_eq!(hkdf.expand(&info, 255 * 32).map(|k| k.len()), Ok(255 * 32));
+        assert_eq!(
+            hkdf.expand(&info, 255 * 32 + 1),
+            Err(CryptoError::InvalidLength)
+        );
+    }
+}
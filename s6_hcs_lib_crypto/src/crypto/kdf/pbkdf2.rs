@@ -0,0 +1,90 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::hash::Digest;
+use crate::crypto::mac::{Mac, HMAC};
+
+// PBKDF2 with HMAC from RFC 8018. Stretches a password into len bytes,
+// iterations sets how much work each guess costs.
+pub fn pbkdf2<D: Digest>(
+    password: &[u8],
+    salt: &[u8],
+    iterations: u32,
+    len: usize,
+) -> Result<Vec<u8>, CryptoError> {
+    if iterations == 0 {
+        return Err(CryptoError::InvalidParameters);
+    }
+    if len == 0 {
+        return Err(CryptoError::InvalidLength);
+    }
+    let mac = HMAC::<D>::with_key(password);
+    let mut out = Vec::with_capacity(len);
+    for i in 1..=len.div_ceil(D::OUTPUT_SIZE) as u32 {
+        let mut u = mac.clone();
+        u.update(salt);
+        u.update(&i.to_be_bytes());
+        let mut u = u.finalize();
+        let mut block = u.clone();
+        for _ in 1..iterations {
+            let mut next = mac.clone();
+            next.update(&u);
+            u = next.finalize();
+            block.iter_mut().zip(&u).for_each(|(b, x)| *b ^= x);
+        }
+        out.extend_from_slice(&block);
+    }
+    out.truncate(len);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::SHA256;
+    use crate::crypto::symmetric_algs::{Block, BlockCipher, DEAL128};
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_pbkdf2() {
+        // the RFC 6070 inputs with SHA-256
+        let cases = [
+            (
+                &b"password"[..],
+                &b"salt"[..],
+                1,
+                "120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b",
+            ),
+            (
+                b"password",
+                b"salt",
+                2,
+                "ae4d0c95af6b46d32d0adff928f06dd02a303f8ef3c251dfd6e2d85a95474c43",
+            ),
+            (
+                b"password",
+                b"salt",
+                4096,
+                "c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a",
+            ),
+            (
+                b"passwordPASSWORDpassword",
+                b"saltSALTsaltSALTsaltSALTsaltSALTsalt",
+                4096,
+                "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1c635518c7dac47e9",
+            ),
+        ];
+        for (password, salt, iterations, expected) in cases {
+            let expected = hex(expected);
+            let key = pbkdf2::<SHA256>(password, salt, iterations, expected.len());
+            assert_eq!(key, Ok(expected));
+        }
+        assert_eq!(
+            pbkdf2::<SHA256>(b"password", b"salt", 0, 16),
+            Err(CryptoError::InvalidParameters)
+        );
+
+        // a password as a DEAL128 key
+        let key = pbkdf2::<SHA256>(b"password", b"salt", 1000, 16).unwrap();
+        let deal = DEAL128::with_key(u128::from_bytes(&key));
+        assert_eq!(deal.decrypt(deal.encrypt(42)), 42);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::hash::SHA256;
+use crate::crypto::kdf::pbkdf2;
+use rayon::prelude::*;
+
+// 256 MiB, eight times the default. The lanes may all run at once and the
+// cost comes from untrusted headers, so anything beyond is refused.
+const MAX_MEMORY: usize = 1 << 28;
+
+// Cost of scrypt: N = 2^log_n blocks of 128 * r bytes, p independent lanes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ScryptParams {
+    pub log_n: u8,
+    pub r: u32,
+    pub p: u32,
+}
+
+impl ScryptParams {
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, CryptoError> {
+        let params = Self { log_n, r, p };
+        match params.memory() {
+            Some(memory) if log_n > 0 && r > 0 && p > 0 && memory <= MAX_MEMORY => Ok(params),
+            _ => Err(CryptoError::InvalidParameters),
+        }
+    }
+
+    fn memory(&self) -> Option<usize> {
+        1usize
+            .checked_shl(self.log_n as u32)?
+            .checked_mul(128)?
+            .checked_mul(self.r as usize)?
+            .checked_mul(self.p as usize)
+    }
+}
+
+// 32 MiB per guess, about a tenth of a second on a desktop
+impl Default for ScryptParams {
+    fn default() -> Self {
+        Self {
+            log_n: 15,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+fn salsa20_8(block: &mut [u32; 16]) {
+    let mut x = *block;
+    let quarter = |x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize| {
+        x[b] ^= x[a].wrapping_add(x[d]).rotate_left(7);
+        x[c] ^= x[b].wrapping_add(x[a]).rotate_left(9);
+        x[d] ^= x[c].wrapping_add(x[b]).rotate_left(13);
+        x[a] ^= x[d].wrapping_add(x[c]).rotate_left(18);
+    };
+    for _ in 0..4 {
+        // columns, then rows
+        quarter(&mut x, 0, 4, 8, 12);
+        quarter(&mut x, 5, 9, 13, 1);
+        quarter(&mut x, 10, 14, 2, 6);
+        quarter(&mut x, 15, 3, 7, 11);
+        quarter(&mut x, 0, 1, 2, 3);
+        quarter(&mut x, 5, 6, 7, 4);
+        quarter(&mut x, 10, 11, 8, 9);
+        quarter(&mut x, 15, 12, 13, 14);
+    }
+    for (b, x) in block.iter_mut().zip(x) {
+        *b = b.wrapping_add(x);
+    }
+}
+
+// b holds 2r blocks of 16 words
+fn block_mix(b: &mut [u32], scratch: &mut [u32]) {
+    let blocks = b.len() / 16;
+    let mut x: [u32; 16] = b[b.len() - 16..].try_into().unwrap();
+    for i in 0..blocks {
+        x.iter_mut().zip(&b[16 * i..]).for_each(|(x, b)| *x ^= b);
+        salsa20_8(&mut x);
+        // even blocks go to the first half, odd ones to the second
+        let dst = (i / 2 + (i % 2) * blocks / 2) * 16;
+        scratch[dst..dst + 16].copy_from_slice(&x);
+    }
+    b.copy_from_slice(scratch);
+}
+
+fn ro_mix(lane: &mut [u8], log_n: u8) {
+    let n = 1usize << log_n;
+    let mut x: Vec<u32> = lane
+        .chunks(4)
+        .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+        .collect();
+    let len = x.len();
+    let mut scratch = vec![0; len];
+    let mut v = vec![0; n * len];
+    for i in 0..n {
+        v[i * len..(i + 1) * len].copy_from_slice(&x);
+        block_mix(&mut x, &mut scratch);
+    }
+    for _ in 0..n {
+        // integerify, the first word of the last block
+        let j = x[len - 16] as usize & (n - 1);
+        x.iter_mut().zip(&v[j * len..]).for_each(|(x, v)| *x ^= v);
+        block_mix(&mut x, &mut scratch);
+    }
+    for (out, w) in lane.chunks_mut(4).zip(x) {
+        out.copy_from_slice(&w.to_le_bytes());
+    }
+}
+
+// RFC 7914. Memory-hard, so guessing passwords on custom hardware costs
+// as much memory per guess as it does here.
+pub fn scrypt(
+    password: &[u8],
+    salt: &[u8],
+    params: ScryptParams,
+    len: usize,
+) -> Result<Vec<u8>, CryptoError> {
+    let params = ScryptParams::new(params.log_n, params.r, params.p)?;
+    let lane = 128 * params.r as usize;
+    let mut b = pbkdf2::<SHA256>(password, salt, 1, params.p as usize * lane)?;
+    b.par_chunks_mut(lane)
+        .for_each(|lane| ro_mix(lane, params.log_n));
+    pbkdf2::<SHA256>(password, &b, 1, len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_scrypt() {
+        // RFC 7914 section 12
+        let params = ScryptParams::new(4, 1, 1).unwrap();
+        assert_eq!(
+            scrypt(b"", b"", params, 64),
+            Ok(hex(concat!(
+                "77d6576238657b203b19ca42c18a0497f16b4844e3074ae8dfdffa3fede21442",
+                "fcd0069ded0948f8326a753a0fc81f17e8d3e0fb2e0d3628cf35e20c38d18906"
+            )))
+        );
+        let params = ScryptParams::new(10, 8, 16).unwrap();
+        assert_eq!(
+            scrypt(b"password", b"NaCl", params, 64),
+            Ok(hex(concat!(
+                "fdbabe1c9d3472007856e7190d01e9fe7c6ad7cbc8237830e77376634b373162",
+                "2eaf30d92e22a3886ff109279d9830dac727afb94a83ee6d8360cbdfa2cc0640"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_scrypt_params() {
+        assert!(ScryptParams::new(0, 8, 1).is_err());
+        assert!(ScryptParams::new(14, 0, 1).is_err());
+        assert!(ScryptParams::new(64, 8, 1).is_err());
+        assert_eq!(ScryptParams::new(15, 8, 1), Ok(ScryptParams::default()));
+        // stronger than the default, 64 MiB and 256 MiB
+        assert!(ScryptParams::new(16, 8, 1).is_ok());
+        assert!(ScryptParams::new(15, 8, 8).is_ok());
+        // 512 MiB and 2 GiB
+        assert!(ScryptParams::new(15, 8, 16).is_err());
+        assert!(ScryptParams::new(21, 8, 1).is_err());
+    }
+}
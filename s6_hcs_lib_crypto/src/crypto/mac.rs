@@ -0,0 +1,9 @@
+mod base;
+mod cmac;
+mod hmac;
+mod poly1305;
+
+pub use base::{ct_eq, Mac};
+pub use cmac::CMAC;
+pub use hmac::HMAC;
+pub use poly1305::Poly1305;
@@ -0,0 +1,112 @@
+use crate::crypto::mac::Mac;
+use crate::crypto::symmetric_algs::BlockCipher;
+
+// CMAC (OMAC1) from NIST SP 800-38B over a 128-bit block cipher
+pub struct CMAC<C: BlockCipher<Block = u128>> {
+    cipher: C,
+    k1: u128,
+    k2: u128,
+    x: u128,
+    // the last block is held back until it is known whether more data follows
+    buffer: Vec<u8>,
+}
+
+// doubling in GF(2^128)
+fn dbl(x: u128) -> u128 {
+    (x << 1) ^ if x >> 127 == 1 { 0x87 } else { 0 }
+}
+
+fn to_block(bytes: &[u8]) -> u128 {
+    u128::from_be_bytes(bytes.try_into().unwrap())
+}
+
+impl<C: BlockCipher<Block = u128>> CMAC<C> {
+    pub fn with_key(key: C::Key) -> Self {
+        let cipher = C::with_key(key);
+        let k1 = dbl(cipher.encrypt(0));
+        Self {
+            cipher,
+            k1,
+            k2: dbl(k1),
+            x: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn mac(key: C::Key, data: &[u8]) -> Vec<u8> {
+        let mut mac = Self::with_key(key);
+        mac.update(data);
+        mac.finalize()
+    }
+}
+
+impl<C: BlockCipher<Block = u128>> Mac for CMAC<C> {
+    fn update(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+        let full = (self.buffer.len().saturating_sub(1) / 16) * 16;
+        for block in self.buffer[..full].chunks(16) {
+            self.x = self.cipher.encrypt(self.x ^ to_block(block));
+        }
+        self.buffer.drain(..full);
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        let last = match self.buffer.len() {
+            16 => to_block(&self.buffer) ^ self.k1,
+            _ => {
+                self.buffer.push(0x80);
+                self.buffer.resize(16, 0);
+                to_block(&self.buffer) ^ self.k2
+            }
+        };
+        self.cipher.encrypt(self.x ^ last).to_be_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::symmetric_algs::{AES128, DEAL128};
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_cmac() {
+        // RFC 4493 section 4
+        let key = 0x2b7e_1516_28ae_d2a6_abf7_1588_09cf_4f3c;
+        let data = hex(concat!(
+            "6bc1bee22e409f96e93d7e117393172a",
+            "ae2d8a571e03ac9c9eb76fac45af8e51",
+            "30c81c46a35ce411e5fbc1191a0a52ef",
+            "f69f2445df4f9b17ad2b417be66c3710"
+        ));
+        let cases = [
+            (0, "bb1d6929e95937287fa37d129b756746"),
+            (16, "070a16b46b4d4144f79bdd9dd04a287c"),
+            (40, "dfa66747de9ae63030ca32611497c827"),
+            (64, "51f0bebf7e3b9d92fc49741779363cfe"),
+        ];
+        for (len, expected) in cases {
+            assert_eq!(CMAC::<AES128>::mac(key, &data[..len]), hex(expected));
+
+            let mut mac = CMAC::<AES128>::with_key(key);
+            for chunk in data[..len].chunks(7) {
+                mac.update(chunk);
+            }
+            assert!(mac.verify(&hex(expected)));
+        }
+    }
+
+    #[test]
+    fn test_cmac_deal() {
+        let key = DEAL128::generate_key(&mut OsRng);
+        let tag = CMAC::<DEAL128>::mac(key, b"file contents");
+        let mut mac = CMAC::<DEAL128>::with_key(key);
+        mac.update(b"file contents");
+        assert!(mac.verify(&tag));
+
+        let mut mac = CMAC::<DEAL128>::with_key(key);
+        mac.update(b"file contents!");
+        assert!(!mac.verify(&tag));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::crypto::hash::Digest;
+use crate::crypto::mac::Mac;
+
+// RFC 2104
+#[derive(Clone)]
+pub struct HMAC<D: Digest> {
+    inner: D,
+    outer: D,
+}
+
+impl<D: Digest> HMAC<D> {
+    pub fn with_key(key: &[u8]) -> Self {
+        let mut block = match key.len() > D::BLOCK_SIZE {
+            true => D::digest(key),
+            false => key.to_vec(),
+        };
+        block.resize(D::BLOCK_SIZE, 0);
+
+        let pad = |byte: u8| -> Vec<u8> { block.iter().map(|k| k ^ byte).collect() };
+        let (mut inner, mut outer) = (D::new(), D::new());
+        inner.update(&pad(0x36));
+        outer.update(&pad(0x5c));
+        Self { inner, outer }
+    }
+
+    pub fn mac(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = Self::with_key(key);
+        mac.update(data);
+        mac.finalize()
+    }
+}
+
+impl<D: Digest> Mac for HMAC<D> {
+    fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        self.outer.update(&self.inner.finalize());
+        self.outer.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::{SHA256, SHA3_256, SHA512};
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_hmac() {
+        // RFC 4231 test cases 1, 2 and 6
+        let cases: [(&[u8], &[u8], &str); 3] = [
+            (
+                &[0x0b; 20],
+                b"Hi There",
+                "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            ),
+            (
+                b"Jefe",
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, data, expected) in cases {
+            assert_eq!(HMAC::<SHA256>::mac(key, data), hex(expected));
+        }
+
+        assert_eq!(
+            HMAC::<SHA512>::mac(&[0x0b; 20], b"Hi There"),
+            hex(concat!(
+                "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cde",
+                "daa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854"
+            ))
+        );
+        assert_eq!(
+            HMAC::<SHA3_256>::mac(b"Jefe", b"what do ya want for nothing?"),
+            hex("c7d4072e788877ae3596bbb0da73b887c9171f93095b294ae857fbe2645e1ba5")
+        );
+    }
+
+    #[test]
+    fn test_hmac_verify() {
+        let tag = HMAC::<SHA256>::mac(b"key", b"message");
+        let mut mac = HMAC::<SHA256>::with_key(b"key");
+        mac.update(b"mess");
+        mac.update(b"age");
+        assert!(mac.clone().verify(&tag));
+        assert!(!mac.clone().verify(&tag[..16]));
+
+        let mut bad = tag.clone();
+        bad[31] ^= 1;
+        assert!(!mac.verify(&bad));
+    }
+}
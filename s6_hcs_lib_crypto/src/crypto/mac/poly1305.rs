@@ -0,0 +1,177 @@
+use crate::crypto::hash::absorb;
+use crate::crypto::mac::Mac;
+
+const MASK: u32 = 0x3ff_ffff;
+
+fn le32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes[..4].try_into().unwrap())
+}
+
+// RFC 8439, the accumulator and r are kept in 26-bit limbs so products fit
+// in a u64. The key is one-time, it must never authenticate two messages.
+#[derive(Clone)]
+pub struct Poly1305 {
+    r: [u32; 5],
+    h: [u32; 5],
+    s: [u32; 4],
+    buffer: Vec<u8>,
+}
+
+impl Poly1305 {
+    pub const KEY_SIZE: usize = 32;
+    pub const TAG_SIZE: usize = 16;
+
+    pub fn with_key(key: &[u8; 32]) -> Self {
+        // clamped r
+        let r = [
+            le32(&key[0..]) & 0x3ff_ffff,
+            (le32(&key[3..]) >> 2) & 0x3ff_ff03,
+            (le32(&key[6..]) >> 4) & 0x3ff_c0ff,
+            (le32(&key[9..]) >> 6) & 0x3f0_3fff,
+            (le32(&key[12..]) >> 8) & 0x00f_ffff,
+        ];
+        let s = std::array::from_fn(|i| le32(&key[16 + 4 * i..]));
+        Self {
+            r,
+            h: [0; 5],
+            s,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn mac(key: &[u8; 32], data: &[u8]) -> Vec<u8> {
+        let mut mac = Self::with_key(key);
+        mac.update(data);
+        mac.finalize()
+    }
+
+    // h = (h + block) * r mod 2^130 - 5, hibit is the 2^128 bit of the block
+    fn block(h: &mut [u32; 5], r: &[u32; 5], block: &[u8], hibit: u32) {
+        h[0] += le32(&block[0..]) & MASK;
+        h[1] += (le32(&block[3..]) >> 2) & MASK;
+        h[2] += (le32(&block[6..]) >> 4) & MASK;
+        h[3] += (le32(&block[9..]) >> 6) & MASK;
+        h[4] += (le32(&block[12..]) >> 8) | hibit;
+
+        // 2^130 wraps around to 5
+        let [r0, r1, r2, r3, r4] = r.map(|r| r as u64);
+        let [s1, s2, s3, s4] = [r1 * 5, r2 * 5, r3 * 5, r4 * 5];
+        let [h0, h1, h2, h3, h4] = h.map(|h| h as u64);
+        let mut d = [
+            h0 * r0 + h1 * s4 + h2 * s3 + h3 * s2 + h4 * s1,
+            h0 * r1 + h1 * r0 + h2 * s4 + h3 * s3 + h4 * s2,
+            h0 * r2 + h1 * r1 + h2 * r0 + h3 * s4 + h4 * s3,
+            h0 * r3 + h1 * r2 + h2 * r1 + h3 * r0 + h4 * s4,
+            h0 * r4 + h1 * r3 + h2 * r2 + h3 * r1 + h4 * r0,
+        ];
+        for i in 0..4 {
+            d[i + 1] += d[i] >> 26;
+            h[i] = d[i] as u32 & MASK;
+        }
+        h[4] = d[4] as u32 & MASK;
+        h[0] += (d[4] >> 26) as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+    }
+}
+
+impl Mac for Poly1305 {
+    fn update(&mut self, data: &[u8]) {
+        let (h, r) = (&mut self.h, &self.r);
+        absorb(&mut self.buffer, 16, data, |block| {
+            Self::block(h, r, block, 1 << 24)
+        });
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        if !self.buffer.is_empty() {
+            // a short last block gets its 1 bit right after the data
+            let mut block = std::mem::take(&mut self.buffer);
+            block.push(1);
+            block.resize(16, 0);
+            Self::block(&mut self.h, &self.r, &block, 0);
+        }
+
+        let mut h = self.h;
+        for i in 1..5 {
+            h[i] += h[i - 1] >> 26;
+            h[i - 1] &= MASK;
+        }
+        h[0] += (h[4] >> 26) * 5;
+        h[4] &= MASK;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+
+        // g = h + 5 - 2^130, taken instead of h when it does not go negative
+        let mut g = [0u32; 5];
+        let mut carry = 5;
+        for i in 0..5 {
+            g[i] = h[i] + carry;
+            carry = g[i] >> 26;
+            g[i] &= MASK;
+        }
+        g[4] = g[4].wrapping_add(carry << 26).wrapping_sub(1 << 26);
+        let use_g = (g[4] >> 31).wrapping_sub(1);
+        for i in 0..5 {
+            h[i] = (h[i] & !use_g) | (g[i] & use_g);
+        }
+
+        // back to 4 words, then add s
+        let words = [
+            h[0] | (h[1] << 26),
+            (h[1] >> 6) | (h[2] << 20),
+            (h[2] >> 12) | (h[3] << 14),
+            (h[3] >> 18) | (h[4] << 8),
+        ];
+        let mut carry = 0u64;
+        let mut tag = Vec::with_capacity(16);
+        for (w, s) in words.into_iter().zip(self.s) {
+            let sum = w as u64 + s as u64 + carry;
+            tag.extend_from_slice(&(sum as u32).to_le_bytes());
+            carry = sum >> 32;
+        }
+        tag
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_poly1305() {
+        // RFC 8439 section 2.5.2
+        let k = hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+        let k = k.try_into().unwrap();
+        let data = b"Cryptographic Forum Research Group";
+        let expected = hex("a8061dc1305136c6c22b8baf0c0127a9");
+        assert_eq!(Poly1305::mac(&k, data), expected);
+        let mut mac = Poly1305::with_key(&k);
+        for chunk in data.chunks(5) {
+            mac.update(chunk);
+        }
+        assert!(mac.verify(&expected));
+
+        // appendix A.3, vectors 5 to 7 need the final reduction
+        let three = hex("03000000000000000000000000000000");
+        let mut k = [0u8; 32];
+        k[0] = 2;
+        assert_eq!(Poly1305::mac(&k, &[0xff; 16]), three);
+        k[16..].copy_from_slice(&[0xff; 16]);
+        let mut data = [0u8; 16];
+        data[0] = 2;
+        assert_eq!(Poly1305::mac(&k, &data), three);
+        let mut k = [0u8; 32];
+        k[0] = 1;
+        let data = hex(concat!(
+            "ffffffffffffffffffffffffffffffff",
+            "f0ffffffffffffffffffffffffffffff",
+            "11000000000000000000000000000000"
+        ));
+        assert_eq!(
+            Poly1305::mac(&k, &data),
+            hex("05000000000000000000000000000000")
+        );
+    }
+}
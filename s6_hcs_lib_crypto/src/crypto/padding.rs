@@ -1,14 +1,25 @@
+mod ansi_x923;
 mod base;
+mod iso10126;
+mod iso7816;
+mod none;
 mod pkcs7;
+mod zero;
 
-pub use base::PaddingAlgorithm;
+pub use ansi_x923::PaddingANSIX923;
+pub use base::{PaddingAlgorithm, PaddingError};
+pub use iso10126::PaddingISO10126;
+pub use iso7816::PaddingISO7816;
+pub use none::PaddingNone;
 pub use pkcs7::PaddingPKSC7;
+pub use zero::PaddingZero;
 
 // Convert Vec<u128> to Vec<u8>
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::crypto::padding::base::PaddingAlgorithm;
+    use crate::crypto::rng::{seeded_rng, OsRng};
 
     #[test]
     fn test_pkcs7() {
@@ -16,9 +27,115 @@ mod tests {
         let data = vec![vec![5u8; 8], vec![4u8; 5], vec![4u8; 163]];
         for d in data {
             let mut r = d.clone();
-            padder.apply_padding(&mut r);
-            padder.remove_padding(&mut r);
+            padder.apply_padding(&mut r, &mut OsRng);
+            padder.remove_padding(&mut r).unwrap();
             assert_eq!(d, r);
         }
     }
+
+    fn test_roundtrip<P: PaddingAlgorithm>() {
+        let padder = P::with_block_size(16);
+        for len in [0, 1, 15, 16, 17, 163] {
+            let d: Vec<u8> = (1..=len).map(|i| i as u8 | 1).collect();
+            let mut r = d.clone();
+            padder.apply_padding(&mut r, &mut OsRng);
+            assert_eq!(r.len() % 16, 0);
+            assert!(r.len() > d.len());
+            padder.remove_padding(&mut r).unwrap();
+            assert_eq!(d, r);
+        }
+    }
+
+    #[test]
+    fn test_schemes() {
+        test_roundtrip::<PaddingANSIX923>();
+        test_roundtrip::<PaddingISO10126>();
+        test_roundtrip::<PaddingISO7816>();
+        test_roundtrip::<PaddingZero>();
+
+        let mut data = vec![0xaa; 5];
+        PaddingANSIX923::with_block_size(8).apply_padding(&mut data, &mut OsRng);
+        assert_eq!(data, [0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0, 0, 3]);
+
+        let mut data = vec![0xaa; 5];
+        PaddingISO7816::with_block_size(8).apply_padding(&mut data, &mut OsRng);
+        assert_eq!(data, [0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0x80, 0, 0]);
+
+        let mut data = vec![0xaa; 5];
+        PaddingZero::with_block_size(8).apply_padding(&mut data, &mut OsRng);
+        assert_eq!(data, [0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0, 0, 0]);
+
+        let mut data = vec![0xaa; 5];
+        PaddingISO10126::with_block_size(8).apply_padding(&mut data, &mut seeded_rng(3));
+        assert_eq!((data.len(), data[7]), (8, 3));
+        // the filler comes from the rng passed in
+        let mut again = vec![0xaa; 5];
+        PaddingISO10126::with_block_size(8).apply_padding(&mut again, &mut seeded_rng(3));
+        assert_eq!(data, again);
+    }
+
+    #[test]
+    fn test_zero_trailing_data() {
+        // zeros at the end of the data cannot be told from padding and go
+        // with it, unless a full block of padding follows them
+        let padder = PaddingZero::with_block_size(8);
+        let mut data = vec![0xaa, 0xbb, 0, 0];
+        padder.apply_padding(&mut data, &mut OsRng);
+        padder.remove_padding(&mut data).unwrap();
+        assert_eq!(data, [0xaa, 0xbb]);
+
+        let mut data = vec![0xaa, 0, 0, 0, 0, 0, 0, 0];
+        padder.apply_padding(&mut data, &mut OsRng);
+        assert_eq!(data.len(), 16);
+        padder.remove_padding(&mut data).unwrap();
+        assert_eq!(data, [0xaa, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    fn remove<P: PaddingAlgorithm>(data: &[u8]) -> Result<Vec<u8>, PaddingError> {
+        let mut data = data.to_vec();
+        P::with_block_size(4).remove_padding(&mut data)?;
+        Ok(data)
+    }
+
+    #[test]
+    fn test_invalid_padding() {
+        use PaddingError::*;
+
+        for res in [
+            remove::<PaddingPKSC7>(&[]),
+            remove::<PaddingPKSC7>(&[1, 2, 3]),
+            remove::<PaddingANSIX923>(&[1, 2, 3, 4, 1]),
+            remove::<PaddingISO7816>(&[]),
+            remove::<PaddingZero>(&[0; 5]),
+        ] {
+            assert_eq!(res, Err(InvalidLength));
+        }
+
+        for res in [
+            remove::<PaddingPKSC7>(&[1, 2, 3, 0]),
+            remove::<PaddingPKSC7>(&[1, 2, 3, 5]),
+            remove::<PaddingPKSC7>(&[1, 2, 3, 2]),
+            remove::<PaddingPKSC7>(&[1, 3, 2, 3]),
+            remove::<PaddingANSIX923>(&[1, 2, 1, 3]),
+            remove::<PaddingISO10126>(&[1, 2, 3, 9]),
+            remove::<PaddingISO7816>(&[1, 2, 3, 0]),
+            remove::<PaddingISO7816>(&[0, 0, 0, 0]),
+            remove::<PaddingISO7816>(&[1, 0x80, 1, 0]),
+            remove::<PaddingZero>(&[1, 2, 3, 4]),
+        ] {
+            assert_eq!(res, Err(InvalidPadding));
+        }
+
+        assert_eq!(
+            remove::<PaddingPKSC7>(&[1, 2, 2, 2, 4, 4, 4, 4]),
+            Ok(vec![1, 2, 2, 2])
+        );
+        assert_eq!(remove::<PaddingANSIX923>(&[1, 2, 0, 2]), Ok(vec![1, 2]));
+        assert_eq!(remove::<PaddingISO10126>(&[1, 9, 9, 3]), Ok(vec![1]));
+        assert_eq!(
+            remove::<PaddingISO7816>(&[1, 0x80, 0x80, 0]),
+            Ok(vec![1, 0x80])
+        );
+        assert_eq!(remove::<PaddingZero>(&[1, 0, 1, 0]), Ok(vec![1, 0, 1]));
+    }
 }
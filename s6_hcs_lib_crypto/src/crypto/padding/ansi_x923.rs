@@ -0,0 +1,28 @@
+use crate::crypto::padding::base::{
+    check_length_byte, ct_eq, last_block, PaddingAlgorithm, PaddingError,
+};
+use crate::crypto::rng::SecureRng;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PaddingANSIX923 {
+    block_size: u8,
+}
+
+impl PaddingAlgorithm for PaddingANSIX923 {
+    fn apply_padding(&self, input: &mut Vec<u8>, _rng: &mut dyn SecureRng) {
+        let pad_bytes = self.block_size - (input.len() % (self.block_size as usize)) as u8;
+        input.extend(vec![0; pad_bytes as usize - 1]);
+        input.push(pad_bytes);
+    }
+
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        let block = last_block(input, self.block_size)?;
+        let n = check_length_byte(block, |byte, _| ct_eq(byte, 0))?;
+        input.truncate(input.len() - n);
+        Ok(())
+    }
+
+    fn with_block_size(size: u8) -> Self {
+        Self { block_size: size }
+    }
+}
@@ -1,9 +1,54 @@
+use crate::crypto::rng::SecureRng;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PaddingError {
+    InvalidLength,
+    InvalidPadding,
+}
+
 pub trait PaddingAlgorithm
 where
     Self: Copy,
 {
-    fn apply_padding(&self, input: &mut Vec<u8>);
-    fn remove_padding(&self, input: &mut Vec<u8>);
+    // only ISO 10126 draws from rng, for its filler
+    fn apply_padding(&self, input: &mut Vec<u8>, rng: &mut dyn SecureRng);
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError>;
 
     fn with_block_size(size: u8) -> Self;
 }
+
+// 0xff if a == b, 0 otherwise, without branching on the values
+pub(super) fn ct_eq(a: u8, b: u8) -> u8 {
+    (((a ^ b) as u16).wrapping_sub(1) >> 8) as u8
+}
+
+// 0xff if a < b, 0 otherwise
+pub(super) fn ct_lt(a: u8, b: u8) -> u8 {
+    ((a as u16).wrapping_sub(b as u16) >> 8) as u8
+}
+
+// Returns the last block, which is the only one padding may span
+pub(super) fn last_block(input: &[u8], block_size: u8) -> Result<&[u8], PaddingError> {
+    let size = block_size as usize;
+    if input.is_empty() || !input.len().is_multiple_of(size) {
+        return Err(PaddingError::InvalidLength);
+    }
+    Ok(&input[input.len() - size..])
+}
+
+// Checks a trailing length byte n: 1 <= n <= block size, and every other pad
+// byte has to pass `check`. The result does not depend on where a mismatch is.
+pub(super) fn check_length_byte(
+    block: &[u8],
+    check: impl Fn(u8, u8) -> u8,
+) -> Result<usize, PaddingError> {
+    let n = block[block.len() - 1];
+    let mut bad = ct_eq(n, 0) | ct_lt(block.len() as u8, n);
+    for (i, byte) in block.iter().rev().enumerate().skip(1) {
+        bad |= ct_lt(i as u8, n) & !check(*byte, n);
+    }
+    if bad != 0 {
+        return Err(PaddingError::InvalidPadding);
+    }
+    Ok(n as usize)
+}
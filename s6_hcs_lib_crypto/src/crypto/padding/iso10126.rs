@@ -0,0 +1,29 @@
+use crate::crypto::padding::base::{check_length_byte, last_block, PaddingAlgorithm, PaddingError};
+use crate::crypto::rng::SecureRng;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PaddingISO10126 {
+    block_size: u8,
+}
+
+impl PaddingAlgorithm for PaddingISO10126 {
+    fn apply_padding(&self, input: &mut Vec<u8>, rng: &mut dyn SecureRng) {
+        let pad_bytes = self.block_size - (input.len() % (self.block_size as usize)) as u8;
+        let mut filler = vec![0; pad_bytes as usize - 1];
+        rng.fill_bytes(&mut filler);
+        input.extend(filler);
+        input.push(pad_bytes);
+    }
+
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        let block = last_block(input, self.block_size)?;
+        // the filler is random, so only the length byte can be checked
+        let n = check_length_byte(block, |_, _| 0xff)?;
+        input.truncate(input.len() - n);
+        Ok(())
+    }
+
+    fn with_block_size(size: u8) -> Self {
+        Self { block_size: size }
+    }
+}
@@ -0,0 +1,37 @@
+use crate::crypto::padding::base::{ct_eq, last_block, PaddingAlgorithm, PaddingError};
+use crate::crypto::rng::SecureRng;
+
+#[derive(Copy, Clone, Debug)]
+pub struct PaddingISO7816 {
+    block_size: u8,
+}
+
+impl PaddingAlgorithm for PaddingISO7816 {
+    fn apply_padding(&self, input: &mut Vec<u8>, _rng: &mut dyn SecureRng) {
+        let pad_bytes = self.block_size - (input.len() % (self.block_size as usize)) as u8;
+        input.push(0x80);
+        input.extend(vec![0; pad_bytes as usize - 1]);
+    }
+
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        let block = last_block(input, self.block_size)?;
+        // walk the whole block: zeros until the 0x80 marker, anything after it
+        let (mut found, mut bad, mut n) = (0u8, 0u8, 0u8);
+        for (i, byte) in block.iter().rev().enumerate() {
+            let searching = !found;
+            let marker = searching & ct_eq(*byte, 0x80);
+            bad |= searching & !marker & !ct_eq(*byte, 0);
+            n |= marker & (i as u8 + 1);
+            found |= marker;
+        }
+        if (bad | !found) != 0 {
+            return Err(PaddingError::InvalidPadding);
+        }
+        input.truncate(input.len() - n as usize);
+        Ok(())
+    }
+
+    fn with_block_size(size: u8) -> Self {
+        Self { block_size: size }
+    }
+}
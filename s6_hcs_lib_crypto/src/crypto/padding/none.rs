@@ -0,0 +1,18 @@
+use crate::crypto::padding::base::{PaddingAlgorithm, PaddingError};
+use crate::crypto::rng::SecureRng;
+
+// For modes that handle a partial last block themselves
+#[derive(Copy, Clone, Debug)]
+pub struct PaddingNone;
+
+impl PaddingAlgorithm for PaddingNone {
+    fn apply_padding(&self, _input: &mut Vec<u8>, _rng: &mut dyn SecureRng) {}
+
+    fn remove_padding(&self, _input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        Ok(())
+    }
+
+    fn with_block_size(_size: u8) -> Self {
+        Self
+    }
+}
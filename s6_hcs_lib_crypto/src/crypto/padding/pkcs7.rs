@@ -1,4 +1,7 @@
-use crate::crypto::padding::base::PaddingAlgorithm;
+use crate::crypto::padding::base::{
+    check_length_byte, ct_eq, last_block, PaddingAlgorithm, PaddingError,
+};
+use crate::crypto::rng::SecureRng;
 
 #[derive(Copy, Clone, Debug)]
 pub struct PaddingPKSC7 {
@@ -12,7 +15,7 @@ impl Default for PaddingPKSC7 {
 }
 
 impl PaddingAlgorithm for PaddingPKSC7 {
-    fn apply_padding(&self, input: &mut Vec<u8>) {
+    fn apply_padding(&self, input: &mut Vec<u8>, _rng: &mut dyn SecureRng) {
         let leftover_bytes = (input.len() % (self.block_size as usize)) as u8;
         let pad_bytes = self.block_size - leftover_bytes;
 
@@ -23,8 +26,11 @@ impl PaddingAlgorithm for PaddingPKSC7 {
         }
     }
 
-    fn remove_padding(&self, input: &mut Vec<u8>) {
-        input.truncate(input.len() - (input.last().unwrap().clone() as usize))
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        let block = last_block(input, self.block_size)?;
+        let n = check_length_byte(block, ct_eq)?;
+        input.truncate(input.len() - n);
+        Ok(())
     }
 
     fn with_block_size(size: u8) -> Self {
@@ -0,0 +1,33 @@
+use crate::crypto::padding::base::{ct_eq, last_block, PaddingAlgorithm, PaddingError};
+use crate::crypto::rng::SecureRng;
+
+// Trailing zero bytes of the data itself are lost on removal
+#[derive(Copy, Clone, Debug)]
+pub struct PaddingZero {
+    block_size: u8,
+}
+
+impl PaddingAlgorithm for PaddingZero {
+    fn apply_padding(&self, input: &mut Vec<u8>, _rng: &mut dyn SecureRng) {
+        let pad_bytes = self.block_size - (input.len() % (self.block_size as usize)) as u8;
+        input.extend(vec![0; pad_bytes as usize]);
+    }
+
+    fn remove_padding(&self, input: &mut Vec<u8>) -> Result<(), PaddingError> {
+        let block = last_block(input, self.block_size)?;
+        let (mut zeros, mut n) = (0xffu8, 0u8);
+        for byte in block.iter().rev() {
+            zeros &= ct_eq(*byte, 0);
+            n += zeros & 1;
+        }
+        if n == 0 {
+            return Err(PaddingError::InvalidPadding);
+        }
+        input.truncate(input.len() - n as usize);
+        Ok(())
+    }
+
+    fn with_block_size(size: u8) -> Self {
+        Self { block_size: size }
+    }
+}
@@ -1,8 +1,8 @@
+use crate::crypto::rng::SecureRng;
 use num_bigint::{BigInt, RandBigInt};
 use num_integer::Integer;
 use num_traits::{One, Zero};
-use rand::thread_rng;
-use std::ops::{Div, Sub};
+use std::ops::Sub;
 
 fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
     let mut remainder = (a.clone(), b.clone());
@@ -10,7 +10,7 @@ fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
     let mut t = (BigInt::zero(), BigInt::one());
 
     while remainder.1.ne(&BigInt::zero()) {
-        if &remainder.0 < &remainder.1 {
+        if remainder.0 < remainder.1 {
             remainder = (remainder.1.clone(), remainder.0.clone())
         }
         let quotient = &remainder.0 / &remainder.1;
@@ -18,7 +18,7 @@ fn extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
         s = (s.1.clone(), &s.0 - &quotient * &s.1);
         t = (t.1.clone(), &t.0 - &quotient * &t.1);
     }
-    return (remainder.0, s.0, t.0);
+    (remainder.0, s.0, t.0)
 }
 
 fn jacobi_symbol(j: (BigInt, BigInt)) -> i8 {
@@ -35,10 +35,9 @@ fn jacobi_symbol(j: (BigInt, BigInt)) -> i8 {
         }
 
         std::mem::swap(&mut a, &mut n);
-        if {
-            a.clone().mod_floor(&BigInt::from(4)).eq(&BigInt::from(3))
-                && n.clone().mod_floor(&BigInt::from(4)).eq(&BigInt::from(3))
-        } {
+        if a.clone().mod_floor(&BigInt::from(4)).eq(&BigInt::from(3))
+            && n.clone().mod_floor(&BigInt::from(4)).eq(&BigInt::from(3))
+        {
             result = -result;
         }
         a %= n.clone();
@@ -51,7 +50,7 @@ fn jacobi_symbol(j: (BigInt, BigInt)) -> i8 {
     }
 }
 
-pub fn fermat_test(n: &BigInt, i: u64) -> bool {
+pub fn fermat_test(n: &BigInt, i: u64, rng: &mut dyn SecureRng) -> bool {
     if n <= &BigInt::one() || n.is_even() {
         return false;
     }
@@ -60,18 +59,18 @@ pub fn fermat_test(n: &BigInt, i: u64) -> bool {
     }
 
     for _ in 0..i {
-        let a = thread_rng().gen_bigint_range(&BigInt::from(2), &BigInt::from(n.sub(1)));
+        let a = rng.gen_bigint_range(&BigInt::from(2), &n.sub(1));
         if extended_gcd(&a, n).0 != BigInt::one() {
             return false;
         }
-        if a.modpow(&BigInt::from(n.sub(1)), n).ne(&BigInt::one()) {
+        if a.modpow(&n.sub(1), n).ne(&BigInt::one()) {
             return false;
         }
     }
     true
 }
 
-pub fn solovey_strassen_test(n: &BigInt, i: u64) -> bool {
+pub fn solovey_strassen_test(n: &BigInt, i: u64, rng: &mut dyn SecureRng) -> bool {
     if n <= &BigInt::one() || n.is_even() {
         return false;
     }
@@ -80,10 +79,9 @@ pub fn solovey_strassen_test(n: &BigInt, i: u64) -> bool {
     }
 
     for _ in 0..i {
-        let a = thread_rng().gen_bigint_range(&BigInt::from(2), n);
+        let a = rng.gen_bigint_range(&BigInt::from(2), n);
         let x = jacobi_symbol((a.clone(), n.clone()));
-        let tmp: BigInt = n.clone();
-        let tmp: BigInt = BigInt::from(tmp.sub(1)).div(2);
+        let tmp: BigInt = (n - 1) / 2;
         if x.is_zero() || a.modpow(&tmp, n) != BigInt::from(x) {
             return false;
         }
@@ -91,7 +89,7 @@ pub fn solovey_strassen_test(n: &BigInt, i: u64) -> bool {
     true
 }
 
-pub fn miller_rabin_test(n: &BigInt, i: u64) -> bool {
+pub fn miller_rabin_test(n: &BigInt, i: u64, rng: &mut dyn SecureRng) -> bool {
     if n <= &BigInt::one() || n.is_even() {
         return false;
     }
@@ -107,12 +105,12 @@ pub fn miller_rabin_test(n: &BigInt, i: u64) -> bool {
     }
 
     for _ in 0..i {
-        let a = thread_rng().gen_bigint_range(&BigInt::from(2), &BigInt::from(n - 1));
+        let a = rng.gen_bigint_range(&BigInt::from(2), &(n - 1));
 
-        let mut x = a.modpow(&d, &n);
+        let mut x = a.modpow(&d, n);
         let mut y = BigInt::zero();
         for _ in 0..s {
-            y = x.modpow(&BigInt::from(2), &n);
+            y = x.modpow(&BigInt::from(2), n);
             if y == BigInt::one() && x != BigInt::one() && x != n - 1 {
                 return false;
             }
@@ -128,6 +126,7 @@ pub fn miller_rabin_test(n: &BigInt, i: u64) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::rng::seeded_rng;
     use num_bigint::BigInt;
 
     #[test]
@@ -144,22 +143,25 @@ mod tests {
 
     #[test]
     fn test_fermat() {
-        assert!(fermat_test(&BigInt::from(3), 16));
-        assert!(fermat_test(&BigInt::from(397), 6));
-        assert!(!fermat_test(&BigInt::from(4), 100));
+        let mut rng = seeded_rng(0);
+        assert!(fermat_test(&BigInt::from(3), 16, &mut rng));
+        assert!(fermat_test(&BigInt::from(397), 6, &mut rng));
+        assert!(!fermat_test(&BigInt::from(4), 100, &mut rng));
     }
 
     #[test]
     fn test_solovey_strassen() {
-        assert!(solovey_strassen_test(&BigInt::from(3), 16));
-        assert!(solovey_strassen_test(&BigInt::from(397), 6));
-        assert!(!solovey_strassen_test(&BigInt::from(4), 100));
+        let mut rng = seeded_rng(0);
+        assert!(solovey_strassen_test(&BigInt::from(3), 16, &mut rng));
+        assert!(solovey_strassen_test(&BigInt::from(397), 6, &mut rng));
+        assert!(!solovey_strassen_test(&BigInt::from(4), 100, &mut rng));
     }
 
     #[test]
     fn test_miller_rabin() {
-        assert!(miller_rabin_test(&BigInt::from(3), 16));
-        assert!(miller_rabin_test(&BigInt::from(397), 6));
-        assert!(!miller_rabin_test(&BigInt::from(4), 100));
+        let mut rng = seeded_rng(0);
+        assert!(miller_rabin_test(&BigInt::from(3), 16, &mut rng));
+        assert!(miller_rabin_test(&BigInt::from(397), 6, &mut rng));
+        assert!(!miller_rabin_test(&BigInt::from(4), 100, &mut rng));
     }
 }
@@ -0,0 +1,91 @@
+use crate::crypto::error::CryptoError;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Receives how many units of an operation are done out of the total.
+// Units are bytes for data, steps for the key exchange.
+// May be called from several threads at once.
+pub trait Progress: Sync {
+    fn update(&self, done: u64, total: u64);
+}
+
+// no reporting
+impl Progress for () {
+    fn update(&self, _: u64, _: u64) {}
+}
+
+impl<F: Fn(u64, u64) + Sync> Progress for F {
+    fn update(&self, done: u64, total: u64) {
+        self(done, total)
+    }
+}
+
+// Shared flag to stop an operation from another thread. Operations check it
+// between pieces of work and fail once it is set.
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Counts units done for one operation and reports them
+pub struct Tracker<'a> {
+    progress: &'a dyn Progress,
+    cancel: &'a CancelToken,
+    done: AtomicU64,
+    total: u64,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(progress: &'a dyn Progress, cancel: &'a CancelToken, total: u64) -> Self {
+        Self {
+            progress,
+            cancel,
+            done: AtomicU64::new(0),
+            total,
+        }
+    }
+
+    pub fn check(&self) -> Result<(), CryptoError> {
+        match self.cancel.is_cancelled() {
+            true => Err(CryptoError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    pub fn advance(&self, units: u64) -> Result<(), CryptoError> {
+        let done = self.done.fetch_add(units, Ordering::Relaxed) + units;
+        self.progress.update(done, self.total);
+        self.check()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_tracker() {
+        let seen = Mutex::new(Vec::new());
+        let progress = |done, total| seen.lock().unwrap().push((done, total));
+        let cancel = CancelToken::new();
+        let tracker = Tracker::new(&progress, &cancel, 10);
+        assert_eq!(tracker.advance(4), Ok(()));
+        assert_eq!(tracker.advance(6), Ok(()));
+        cancel.clone().cancel();
+        assert_eq!(tracker.check(), Err(CryptoError::Cancelled));
+        assert_eq!(*seen.lock().unwrap(), vec![(4, 10), (10, 10)]);
+    }
+}
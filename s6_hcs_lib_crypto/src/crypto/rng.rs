@@ -0,0 +1,26 @@
+pub use rand::rngs::{OsRng, StdRng};
+pub use rand::{CryptoRng, RngCore, SeedableRng};
+
+// Source of keys, IVs and key exchange secrets. Everything that needs
+// randomness takes one, so the entropy source is picked by the caller.
+pub trait SecureRng: RngCore + CryptoRng {}
+
+impl<R: RngCore + CryptoRng> SecureRng for R {}
+
+// Deterministic generator for reproducible tests, never use it for real keys
+pub fn seeded_rng(seed: u64) -> StdRng {
+    StdRng::seed_from_u64(seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_rng() {
+        let draw = |rng: &mut dyn SecureRng| rng.next_u64();
+        assert_eq!(draw(&mut seeded_rng(7)), draw(&mut seeded_rng(7)));
+        assert_ne!(draw(&mut seeded_rng(7)), draw(&mut seeded_rng(8)));
+        assert_ne!(draw(&mut OsRng), draw(&mut OsRng));
+    }
+}
@@ -1,7 +1,35 @@
+mod base;
+mod chacha20;
+mod chacha20_poly1305;
 mod deal;
 mod deal_modes;
 mod des;
+mod desx;
+mod gcm;
+mod mars;
+mod rc6;
+mod rijndael;
+mod serpent;
+mod stream;
+mod triple_des;
+mod twofish;
+mod xts;
 
-pub use deal::DEAL128;
-pub use deal_modes::DEALMode;
-pub use des::DES;
+pub use base::{Block, BlockCipher};
+pub use chacha20::ChaCha20;
+pub use chacha20_poly1305::{
+    ChaCha20Poly1305, ChaCha20Poly1305Decryptor, ChaCha20Poly1305Encryptor,
+};
+pub use deal::{LegacyDEAL128, DEAL, DEAL128, DEAL192, DEAL256};
+pub use deal_modes::{DEALMode, Decryptor, Encryptor, LegacyRDHDecryptor};
+pub use des::{LegacyDES, DES};
+pub use desx::DESX;
+pub use gcm::{GCMDecryptor, GCMEncryptor, GCM};
+pub use mars::{MARS, MARS128, MARS192, MARS256};
+pub use rc6::{RC6, RC6128, RC6192, RC6256};
+pub use rijndael::{Rijndael, AES128, AES192, AES256};
+pub use serpent::{Serpent, Serpent128, Serpent192, Serpent256};
+pub use stream::{decrypt_stream, encrypt_stream, StreamDecryptor, StreamEncryptor, CHUNK_SIZE};
+pub use triple_des::{TripleDES, TripleDES2, TripleDES3};
+pub use twofish::{Twofish, Twofish128, Twofish192, Twofish256};
+pub use xts::XTS;
@@ -0,0 +1,169 @@
+use crate::crypto::rng::SecureRng;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+pub trait Block
+where
+    Self: Copy + Debug + PartialEq + Hash + Send + Sync,
+{
+    const BYTES: usize;
+
+    fn random(rng: &mut dyn SecureRng) -> Self {
+        let mut bytes = vec![0; Self::BYTES];
+        rng.fill_bytes(&mut bytes);
+        Self::from_bytes(&bytes)
+    }
+
+    fn from_u64(value: u64) -> Self;
+    fn from_bytes(bytes: &[u8]) -> Self;
+    fn to_bytes(self) -> Vec<u8>;
+    fn write_bytes(self, out: &mut [u8]);
+
+    fn xor(self, rhs: Self) -> Self;
+    fn wrapping_add(self, rhs: Self) -> Self;
+    fn wrapping_mul(self, rhs: Self) -> Self;
+    fn low_half(self) -> Self;
+}
+
+pub trait BlockCipher
+where
+    Self: Sized + Sync,
+{
+    type Block: Block;
+    type Key: Block;
+
+    fn generate_key(rng: &mut dyn SecureRng) -> Self::Key {
+        Self::Key::random(rng)
+    }
+
+    fn with_key(key: Self::Key) -> Self;
+    fn encrypt(&self, input: Self::Block) -> Self::Block;
+    fn decrypt(&self, input: Self::Block) -> Self::Block;
+}
+
+macro_rules! impl_block_for_uint {
+    ($t:ty, $half:ty) => {
+        impl Block for $t {
+            const BYTES: usize = std::mem::size_of::<$t>();
+
+            fn from_u64(value: u64) -> Self {
+                value as $t
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Self {
+                <$t>::from_be_bytes(bytes.try_into().unwrap())
+            }
+
+            fn to_bytes(self) -> Vec<u8> {
+                self.to_be_bytes().to_vec()
+            }
+
+            fn write_bytes(self, out: &mut [u8]) {
+                out.copy_from_slice(&self.to_be_bytes())
+            }
+
+            fn xor(self, rhs: Self) -> Self {
+                self ^ rhs
+            }
+
+            fn wrapping_add(self, rhs: Self) -> Self {
+                <$t>::wrapping_add(self, rhs)
+            }
+
+            fn wrapping_mul(self, rhs: Self) -> Self {
+                <$t>::wrapping_mul(self, rhs)
+            }
+
+            fn low_half(self) -> Self {
+                self as $half as $t
+            }
+        }
+    };
+}
+
+impl_block_for_uint!(u64, u32);
+impl_block_for_uint!(u128, u64);
+
+impl<const N: usize> Block for [u64; N] {
+    const BYTES: usize = N * 8;
+
+    fn from_u64(value: u64) -> Self {
+        let mut out = [0; N];
+        out[N - 1] = value;
+        out
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), Self::BYTES);
+        std::array::from_fn(|i| u64::from_be_bytes(bytes[i * 8..(i + 1) * 8].try_into().unwrap()))
+    }
+
+    fn to_bytes(self) -> Vec<u8> {
+        self.iter().flat_map(|w| w.to_be_bytes()).collect()
+    }
+
+    fn write_bytes(self, out: &mut [u8]) {
+        for (w, chunk) in self.iter().zip(out.chunks_mut(8)) {
+            chunk.copy_from_slice(&w.to_be_bytes())
+        }
+    }
+
+    fn xor(self, rhs: Self) -> Self {
+        std::array::from_fn(|i| self[i] ^ rhs[i])
+    }
+
+    fn wrapping_add(self, rhs: Self) -> Self {
+        let mut out = [0; N];
+        let mut carry = false;
+        for i in (0..N).rev() {
+            let (s, c1) = self[i].overflowing_add(rhs[i]);
+            let (s, c2) = s.overflowing_add(carry as u64);
+            out[i] = s;
+            carry = c1 || c2;
+        }
+        out
+    }
+
+    fn wrapping_mul(self, rhs: Self) -> Self {
+        // limbs are big-endian, so index N - 1 holds the least significant word
+        let mut out = [0u64; N];
+        for i in 0..N {
+            let mut carry = 0u128;
+            for j in 0..N - i {
+                let t = out[N - 1 - i - j] as u128
+                    + self[N - 1 - i] as u128 * rhs[N - 1 - j] as u128
+                    + carry;
+                out[N - 1 - i - j] = t as u64;
+                carry = t >> 64;
+            }
+        }
+        out
+    }
+
+    fn low_half(self) -> Self {
+        let mut bytes = self.to_bytes();
+        bytes[..Self::BYTES / 2].fill(0);
+        Self::from_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::random;
+
+    #[test]
+    fn test_limb_arithmetic() {
+        for _ in 0..1024 {
+            let (a, b): (u128, u128) = (random(), random());
+            let (la, lb) = (
+                <[u64; 2]>::from_bytes(&a.to_bytes()),
+                <[u64; 2]>::from_bytes(&b.to_bytes()),
+            );
+            assert_eq!(la.wrapping_add(lb).to_bytes(), a.wrapping_add(b).to_bytes());
+            assert_eq!(la.wrapping_mul(lb).to_bytes(), a.wrapping_mul(b).to_bytes());
+            assert_eq!(la.low_half().to_bytes(), a.low_half().to_bytes());
+            assert_eq!(la.xor(lb).to_bytes(), a.xor(b).to_bytes());
+        }
+    }
+}
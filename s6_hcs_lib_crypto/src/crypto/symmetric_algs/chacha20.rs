@@ -0,0 +1,111 @@
+use rayon::prelude::*;
+
+// "expand 32-byte k"
+const SIGMA: [u32; 4] = [0x61707865, 0x3320646e, 0x79622d32, 0x6b206574];
+
+// RFC 8439 variant, 96-bit nonce and 32-bit block counter
+#[derive(Clone)]
+pub struct ChaCha20 {
+    state: [u32; 16],
+}
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+impl ChaCha20 {
+    pub const KEY_SIZE: usize = 32;
+    pub const NONCE_SIZE: usize = 12;
+    pub const BLOCK_SIZE: usize = 64;
+
+    pub fn new(key: &[u8; 32], nonce: &[u8; 12]) -> Self {
+        let words = |bytes: &[u8]| -> Vec<u32> {
+            bytes
+                .chunks(4)
+                .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+                .collect()
+        };
+        let mut state = [0; 16];
+        state[..4].copy_from_slice(&SIGMA);
+        state[4..12].copy_from_slice(&words(key));
+        state[13..].copy_from_slice(&words(nonce));
+        Self { state }
+    }
+
+    pub fn block(&self, counter: u32) -> [u8; 64] {
+        let mut input = self.state;
+        input[12] = counter;
+        let mut x = input;
+        for _ in 0..10 {
+            // columns, then diagonals
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 1, 5, 9, 13);
+            quarter_round(&mut x, 2, 6, 10, 14);
+            quarter_round(&mut x, 3, 7, 11, 15);
+            quarter_round(&mut x, 0, 5, 10, 15);
+            quarter_round(&mut x, 1, 6, 11, 12);
+            quarter_round(&mut x, 2, 7, 8, 13);
+            quarter_round(&mut x, 3, 4, 9, 14);
+        }
+        let mut out = [0; 64];
+        for (i, (x, input)) in x.into_iter().zip(input).enumerate() {
+            out[4 * i..4 * i + 4].copy_from_slice(&x.wrapping_add(input).to_le_bytes());
+        }
+        out
+    }
+
+    // XORs data with the keystream starting at block counter
+    pub fn apply_keystream(&self, counter: u32, data: &mut [u8]) {
+        data.par_chunks_mut(64).enumerate().for_each(|(i, chunk)| {
+            let ks = self.block(counter.wrapping_add(i as u32));
+            chunk.iter_mut().zip(ks).for_each(|(b, k)| *b ^= k);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::hex;
+
+    #[test]
+    fn test_chacha20() {
+        let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+
+        // RFC 8439 section 2.3.2
+        let nonce = hex("000000090000004a00000000").try_into().unwrap();
+        assert_eq!(
+            ChaCha20::new(&key, &nonce).block(1).to_vec(),
+            hex(concat!(
+                "10f1e7e4d13b5915500fdd1fa32071c4c7d1f4c733c068030422aa9ac3d46c4e",
+                "d2826446079faa0914c2d705d98b02a2b5129cd1de164eb9cbd083e8a2503c4e"
+            ))
+        );
+
+        // section 2.4.2
+        let nonce = hex("000000000000004a00000000").try_into().unwrap();
+        let mut data = concat!(
+            "Ladies and Gentlemen of the class of '99: If I could offer you ",
+            "only one tip for the future, sunscreen would be it."
+        )
+        .as_bytes()
+        .to_vec();
+        ChaCha20::new(&key, &nonce).apply_keystream(1, &mut data);
+        assert_eq!(
+            data,
+            hex(concat!(
+                "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b",
+                "f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8",
+                "07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736",
+                "5af90bbf74a35be6b40b8eedf2785e42874d"
+            ))
+        );
+    }
+}
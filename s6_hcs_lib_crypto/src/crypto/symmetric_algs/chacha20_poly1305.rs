@@ -0,0 +1,361 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::mac::{ct_eq, Mac, Poly1305};
+use crate::crypto::progress::{CancelToken, Progress, Tracker};
+use crate::crypto::rng::SecureRng;
+use crate::crypto::symmetric_algs::{ChaCha20, StreamDecryptor, StreamEncryptor};
+
+// bytes between progress reports and cancellation checks
+const STEP_SIZE: usize = 1 << 20;
+
+// RFC 8439 section 2.8, the 32-bit block counter starts at 1 and must
+// not wrap around
+const MAX_LEN: u64 = ((1 << 32) - 1) * 64;
+
+fn check_len(len: u64) -> Result<(), CryptoError> {
+    match len <= MAX_LEN {
+        true => Ok(()),
+        false => Err(CryptoError::LimitExceeded),
+    }
+}
+
+// RFC 8439 AEAD. Block 0 of the keystream keys Poly1305, the data is
+// encrypted from block 1 on.
+pub struct ChaCha20Poly1305 {
+    key: [u8; 32],
+}
+
+// The tag covers aad | pad | ciphertext | pad | aad length | ciphertext length
+#[derive(Clone)]
+struct Authenticator {
+    mac: Poly1305,
+    aad_len: u64,
+    len: u64,
+}
+
+fn zero_pad(len: u64) -> Vec<u8> {
+    vec![0; (16 - len as usize % 16) % 16]
+}
+
+impl Authenticator {
+    fn new(chacha: &ChaCha20, aad: &[u8]) -> Self {
+        let key = chacha.block(0)[..32].try_into().unwrap();
+        let mut mac = Poly1305::with_key(&key);
+        mac.update(aad);
+        mac.update(&zero_pad(aad.len() as u64));
+        Self {
+            mac,
+            aad_len: aad.len() as u64,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, ciphertext: &[u8]) {
+        self.mac.update(ciphertext);
+        self.len += ciphertext.len() as u64;
+    }
+
+    fn finalize(mut self) -> Vec<u8> {
+        self.mac.update(&zero_pad(self.len));
+        self.mac.update(&self.aad_len.to_le_bytes());
+        self.mac.update(&self.len.to_le_bytes());
+        self.mac.finalize()
+    }
+}
+
+impl ChaCha20Poly1305 {
+    pub const KEY_SIZE: usize = ChaCha20::KEY_SIZE;
+    pub const IV_SIZE: usize = ChaCha20::NONCE_SIZE;
+    pub const TAG_SIZE: usize = Poly1305::TAG_SIZE;
+
+    pub fn generate_key(rng: &mut dyn SecureRng) -> [u8; 32] {
+        let mut key = [0; 32];
+        rng.fill_bytes(&mut key);
+        key
+    }
+
+    pub fn generate_iv(rng: &mut dyn SecureRng) -> Vec<u8> {
+        let mut iv = vec![0; Self::IV_SIZE];
+        rng.fill_bytes(&mut iv);
+        iv
+    }
+
+    pub fn with_key(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+
+    fn chacha(&self, iv: &[u8]) -> Result<ChaCha20, CryptoError> {
+        let nonce = iv.try_into().map_err(|_| CryptoError::InvalidLength)?;
+        Ok(ChaCha20::new(&self.key, nonce))
+    }
+
+    // applies the keystream in steps, reporting progress in between
+    fn apply_tracked(
+        chacha: &ChaCha20,
+        data: &mut [u8],
+        tracker: &Tracker,
+    ) -> Result<(), CryptoError> {
+        for (i, step) in data.chunks_mut(STEP_SIZE).enumerate() {
+            tracker.check()?;
+            chacha.apply_keystream(1 + (i * STEP_SIZE / 64) as u32, step);
+            tracker.advance(step.len() as u64)?;
+        }
+        Ok(())
+    }
+
+    fn tag(chacha: &ChaCha20, aad: &[u8], ciphertext: &[u8]) -> Vec<u8> {
+        let mut auth = Authenticator::new(chacha, aad);
+        auth.update(ciphertext);
+        auth.finalize()
+    }
+
+    pub fn encrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        input: &[u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        check_len(input.len() as u64)?;
+        let chacha = self.chacha(iv)?;
+        let mut output = input.to_vec();
+        let tracker = Tracker::new(progress, cancel, input.len() as u64);
+        Self::apply_tracked(&chacha, &mut output, &tracker)?;
+        let tag = Self::tag(&chacha, aad, &output);
+        Ok((output, tag))
+    }
+
+    pub fn decrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        input: &[u8],
+        tag: &[u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_len(input.len() as u64)?;
+        let chacha = self.chacha(iv)?;
+        if !ct_eq(&Self::tag(&chacha, aad, input), tag) {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        let mut output = input.to_vec();
+        let tracker = Tracker::new(progress, cancel, input.len() as u64);
+        Self::apply_tracked(&chacha, &mut output, &tracker)?;
+        Ok(output)
+    }
+
+    pub fn encryptor(
+        self,
+        iv: &[u8],
+        aad: &[u8],
+    ) -> Result<ChaCha20Poly1305Encryptor, CryptoError> {
+        Ok(ChaCha20Poly1305Encryptor(ChaChaStream::new(self, iv, aad)?))
+    }
+
+    pub fn decryptor(
+        self,
+        iv: &[u8],
+        aad: &[u8],
+    ) -> Result<ChaCha20Poly1305Decryptor, CryptoError> {
+        Ok(ChaCha20Poly1305Decryptor(ChaChaStream::new(self, iv, aad)?))
+    }
+}
+
+struct ChaChaStream {
+    chacha: ChaCha20,
+    iv: Vec<u8>,
+    auth: Authenticator,
+    blocks: u64,
+    buffer: Vec<u8>,
+}
+
+impl ChaChaStream {
+    fn new(aead: ChaCha20Poly1305, iv: &[u8], aad: &[u8]) -> Result<Self, CryptoError> {
+        let chacha = aead.chacha(iv)?;
+        Ok(Self {
+            auth: Authenticator::new(&chacha, aad),
+            chacha,
+            iv: iv.to_vec(),
+            blocks: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    fn take(&mut self, input: &[u8], all: bool) -> Vec<u8> {
+        self.buffer.extend_from_slice(input);
+        let n = if all {
+            self.buffer.len()
+        } else {
+            self.buffer.len() / 64 * 64
+        };
+        self.buffer.drain(..n).collect()
+    }
+
+    // only the last call may end in a partial block
+    fn apply(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        check_len(self.blocks * 64 + data.len() as u64)?;
+        let counter = (self.blocks as u32).wrapping_add(1);
+        self.chacha.apply_keystream(counter, data);
+        self.blocks += data.len().div_ceil(64) as u64;
+        Ok(())
+    }
+
+    fn tag(&self) -> Vec<u8> {
+        self.auth.clone().finalize()
+    }
+}
+
+pub struct ChaCha20Poly1305Encryptor(ChaChaStream);
+
+pub struct ChaCha20Poly1305Decryptor(ChaChaStream);
+
+impl StreamEncryptor for ChaCha20Poly1305Encryptor {
+    fn iv(&self) -> Vec<u8> {
+        self.0.iv.clone()
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(input, false);
+        self.0.apply(&mut data)?;
+        self.0.auth.update(&data);
+        Ok(data)
+    }
+
+    fn finalize(&mut self) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        let mut data = self.0.take(&[], true);
+        self.0.apply(&mut data)?;
+        self.0.auth.update(&data);
+        Ok((data, self.0.tag()))
+    }
+}
+
+// Plaintext is released before the tag is verified, see decrypt_stream
+impl StreamDecryptor for ChaCha20Poly1305Decryptor {
+    fn tag_size(&self) -> usize {
+        ChaCha20Poly1305::TAG_SIZE
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(input, false);
+        self.0.auth.update(&data);
+        self.0.apply(&mut data)?;
+        Ok(data)
+    }
+
+    fn finalize(&mut self, tag: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(&[], true);
+        self.0.auth.update(&data);
+        self.0.apply(&mut data)?;
+        if !ct_eq(&self.0.tag(), tag) {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::test_util::hex;
+    use rand::random;
+
+    #[test]
+    fn test_chacha20_poly1305() {
+        // RFC 8439 section 2.8.2
+        let key = std::array::from_fn(|i| 0x80 + i as u8);
+        let aead = ChaCha20Poly1305::with_key(key);
+        let iv = hex("070000004041424344454647");
+        let aad = hex("50515253c0c1c2c3c4c5c6c7");
+        let pt = concat!(
+            "Ladies and Gentlemen of the class of '99: If I could offer you ",
+            "only one tip for the future, sunscreen would be it."
+        )
+        .as_bytes();
+        let (ct, tag) = aead
+            .encrypt(&iv, &aad, pt, &(), &CancelToken::new())
+            .unwrap();
+        assert_eq!(
+            ct,
+            hex(concat!(
+                "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6",
+                "3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36",
+                "92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+                "3ff4def08e4b7a9de576d26586cec64b6116"
+            ))
+        );
+        assert_eq!(tag, hex("1ae10b594f09e26a7e902ecbd0600691"));
+        assert_eq!(
+            aead.decrypt(&iv, &aad, &ct, &tag, &(), &CancelToken::new()),
+            Ok(pt.to_vec())
+        );
+        assert_eq!(
+            aead.decrypt(&iv, &[], &ct, &tag, &(), &CancelToken::new()),
+            Err(CryptoError::AuthenticationFailed)
+        );
+        assert!(aead
+            .encrypt(&iv[..8], &aad, pt, &(), &CancelToken::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_chacha20_poly1305_stream() {
+        let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+        let iv = ChaCha20Poly1305::generate_iv(&mut OsRng);
+        let data: Vec<u8> = (0..1000).map(|_| random()).collect();
+        let (ct, tag) = ChaCha20Poly1305::with_key(key)
+            .encrypt(&iv, b"aad", &data, &(), &CancelToken::new())
+            .unwrap();
+
+        let mut enc = ChaCha20Poly1305::with_key(key)
+            .encryptor(&iv, b"aad")
+            .unwrap();
+        let mut out = Vec::new();
+        for chunk in data.chunks(37) {
+            out.extend(enc.update(chunk).unwrap());
+        }
+        let (tail, stream_tag) = enc.finalize().unwrap();
+        out.extend(tail);
+        assert_eq!(out, ct);
+        assert_eq!(stream_tag, tag);
+
+        let mut dec = ChaCha20Poly1305::with_key(key)
+            .decryptor(&iv, b"aad")
+            .unwrap();
+        let mut out = Vec::new();
+        for chunk in ct.chunks(53) {
+            out.extend(dec.update(chunk).unwrap());
+        }
+        out.extend(dec.finalize(&stream_tag).unwrap());
+        assert_eq!(out, data);
+
+        let mut dec = ChaCha20Poly1305::with_key(key)
+            .decryptor(&iv, b"aad")
+            .unwrap();
+        dec.update(&ct).unwrap();
+        assert_eq!(
+            dec.finalize(&[0; 16]),
+            Err(CryptoError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_limit() {
+        assert_eq!(check_len(MAX_LEN), Ok(()));
+        assert_eq!(check_len(MAX_LEN + 1), Err(CryptoError::LimitExceeded));
+
+        // the last block uses counter 2^32 - 1, the next one would wrap to 0
+        let aead = ChaCha20Poly1305::with_key([0; 32]);
+        let mut enc = aead.encryptor(&[0; 12], &[]).unwrap();
+        enc.0.blocks = MAX_LEN / 64 - 1;
+        assert!(enc.update(&[0; 64]).is_ok());
+        assert_eq!(enc.update(&[0; 64]), Err(CryptoError::LimitExceeded));
+        assert!(enc.finalize().is_ok());
+
+        let aead = ChaCha20Poly1305::with_key([0; 32]);
+        let mut dec = aead.decryptor(&[0; 12], &[]).unwrap();
+        dec.0.blocks = MAX_LEN / 64;
+        assert_eq!(dec.update(&[0; 64]), Err(CryptoError::LimitExceeded));
+    }
+}
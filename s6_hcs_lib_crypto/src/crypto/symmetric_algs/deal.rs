@@ -1,76 +1,246 @@
-use crate::crypto::symmetric_algs::DES;
-use rand::random;
+use crate::crypto::rng::SecureRng;
+use crate::crypto::symmetric_algs::{Block, BlockCipher, LegacyDES, DES};
+use std::marker::PhantomData;
 
+// D is the DES the rounds run on, LegacyDES only reads old data
 #[derive(Copy, Clone, Debug, Default)]
-pub struct DEAL128 {
-    round_keys: [u64; 6],
+pub struct DEAL<K: Block, D = DES> {
+    round_keys: [u64; 8],
+    // key schedules of the rounds, expanded once
+    des: [D; 8],
+    rounds: usize,
+    _key: PhantomData<K>,
 }
 
+pub type DEAL128 = DEAL<u128>;
+pub type DEAL192 = DEAL<[u64; 3]>;
+pub type DEAL256 = DEAL<[u64; 4]>;
+
+// DEAL-128 as the client had it before the container, on LegacyDES and with
+// the round keys chained before they are encrypted. Only for reading old files.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LegacyDEAL128(DEAL<u128, LegacyDES>);
+
+// the fixed DES key the round keys are encrypted under
+const SCHEDULE_KEY: u64 = 0x_0123_4567_89ab_cdef;
+
 fn get_bit_at(i: u8) -> u64 {
     assert!((1..=64).contains(&i));
     1u64 << (64 - i)
 }
 
-fn get_round_keys(key: u128) -> [u64; 6] {
-    const K: u64 = 0x_0123_4567_89ab_cdef;
-    let k = ((key >> 64) as u64, key as u64);
-    let mut rk = [0u64; 6];
-    rk[0] = k.0;
-    rk[1] = k.1 ^ rk[0];
-    rk[2] = k.0 ^ rk[1] ^ get_bit_at(1);
-    rk[3] = k.1 ^ rk[2] ^ get_bit_at(2);
-    rk[4] = k.0 ^ rk[3] ^ get_bit_at(4);
-    rk[5] = k.1 ^ rk[4] ^ get_bit_at(8);
-    for i in 0..rk.len() {
-        rk[i] = DES::new(K).encrypt(rk[i]);
+// Knudsen's schedule, RK_i = E_K(K_i ^ <c> ^ RK_(i-1)) with K fixed and
+// <c> = <1>, <2>, <4>, <8> once the key blocks wrap around
+fn get_round_keys<D: BlockCipher<Block = u64, Key = u64>>(key: &[u64], rounds: usize) -> [u64; 8] {
+    let des = D::with_key(SCHEDULE_KEY);
+    let s = key.len();
+    let mut rk = [0u64; 8];
+    let mut prev = 0;
+    for i in 0..rounds {
+        let mut x = key[i % s] ^ prev;
+        if i >= s {
+            x ^= get_bit_at(1 << (i - s));
+        }
+        rk[i] = des.encrypt(x);
+        prev = rk[i];
+    }
+    rk
+}
+
+fn get_legacy_round_keys(key: u128) -> [u64; 8] {
+    let key = [(key >> 64) as u64, key as u64];
+    let mut rk = [0u64; 8];
+    rk[0] = key[0];
+    for i in 1..6 {
+        rk[i] = key[i % 2] ^ rk[i - 1];
+        if i >= 2 {
+            rk[i] ^= get_bit_at(1 << (i - 2));
+        }
+    }
+    let des = LegacyDES::with_key(SCHEDULE_KEY);
+    for k in rk.iter_mut().take(6) {
+        *k = des.encrypt(*k);
     }
     rk
 }
 
-impl DEAL128 {
-    pub fn generate_key() -> u128 {
-        random()
+impl<K: Block, D: BlockCipher<Block = u64, Key = u64>> DEAL<K, D> {
+    fn from_key(key: K) -> Self {
+        assert!([16, 24, 32].contains(&K::BYTES));
+        let key: Vec<u64> = key
+            .to_bytes()
+            .chunks(8)
+            .map(|c| u64::from_be_bytes(c.try_into().unwrap()))
+            .collect();
+        // 6 rounds for DEAL-128 and DEAL-192, 8 for DEAL-256
+        let rounds = if key.len() == 4 { 8 } else { 6 };
+        Self::with_round_keys(get_round_keys::<D>(&key, rounds), rounds)
     }
 
-    pub fn with_key(key: u128) -> Self {
+    fn with_round_keys(round_keys: [u64; 8], rounds: usize) -> Self {
         Self {
-            round_keys: get_round_keys(key),
+            round_keys,
+            des: round_keys.map(D::with_key),
+            rounds,
+            _key: PhantomData,
+        }
+    }
+
+    // every round is a DES encryption under its round key
+    pub fn has_weak_round_key(&self) -> bool {
+        self.round_keys[..self.rounds]
+            .iter()
+            .any(|k| DES::is_weak_key(*k) || DES::is_semi_weak_key(*k))
+    }
+}
+
+impl<K: Block, D: BlockCipher<Block = u64, Key = u64>> BlockCipher for DEAL<K, D> {
+    type Block = u128;
+    type Key = K;
+
+    fn generate_key(rng: &mut dyn SecureRng) -> K {
+        loop {
+            let key = K::random(rng);
+            if !Self::from_key(key).has_weak_round_key() {
+                return key;
+            }
         }
     }
 
-    pub fn encrypt(&self, input: u128) -> u128 {
+    fn with_key(key: K) -> Self {
+        Self::from_key(key)
+    }
+
+    fn encrypt(&self, input: u128) -> u128 {
         let mut x = ((input >> 64) as u64, input as u64);
-        for i in 0..6 {
-            let des = DES::new(self.round_keys[i]);
-            x.1 = des.encrypt(x.0) ^ x.1;
+        for des in &self.des[..self.rounds] {
+            x.1 ^= des.encrypt(x.0);
             x = (x.1, x.0);
         }
         ((x.0 as u128) << 64) | (x.1 as u128)
     }
 
-    pub fn decrypt(&self, input: u128) -> u128 {
+    fn decrypt(&self, input: u128) -> u128 {
         let mut x = ((input >> 64) as u64, input as u64);
-        for i in 0..6 {
+        for des in self.des[..self.rounds].iter().rev() {
             x = (x.1, x.0);
-            let des = DES::new(self.round_keys[5 - i]);
-            x.1 = des.encrypt(x.0) ^ x.1;
+            x.1 ^= des.encrypt(x.0);
         }
         ((x.0 as u128) << 64) | (x.1 as u128)
     }
 }
 
+impl BlockCipher for LegacyDEAL128 {
+    type Block = u128;
+    type Key = u128;
+
+    fn with_key(key: u128) -> Self {
+        Self(DEAL::with_round_keys(get_legacy_round_keys(key), 6))
+    }
+
+    fn encrypt(&self, input: u128) -> u128 {
+        self.0.encrypt(input)
+    }
+
+    fn decrypt(&self, input: u128) -> u128 {
+        self.0.decrypt(input)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::rng::OsRng;
+    use rand::random;
 
-    #[test]
-    fn test_deal() {
-        let key = DEAL128::generate_key();
+    fn test_roundtrip<C: BlockCipher<Block = u128>>() {
+        let key = C::generate_key(&mut OsRng);
         let data = random();
 
-        let cr = DEAL128::with_key(key);
+        let cr = C::with_key(key);
         let enc = cr.encrypt(data);
         let dec = cr.decrypt(enc);
         assert_eq!(data, dec);
     }
+
+    #[test]
+    fn test_deal() {
+        test_roundtrip::<DEAL128>();
+        test_roundtrip::<DEAL192>();
+        test_roundtrip::<DEAL256>();
+
+        // computed apart from this code, with OpenSSL's DES under Knudsen's
+        // schedule, as are the DEAL-192/256 answers in test_schedule
+        let cr = DEAL128::with_key(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        assert_eq!(
+            cr.encrypt(0x0011_2233_4455_6677_8899_aabb_ccdd_eeff),
+            0xe3c3_8906_617a_c2b9_8a58_0519_4600_5a4b
+        );
+        assert_eq!(
+            cr.decrypt(0xe3c3_8906_617a_c2b9_8a58_0519_4600_5a4b),
+            0x0011_2233_4455_6677_8899_aabb_ccdd_eeff
+        );
+
+        // the same key and block under the schedule that applied PC-1 twice
+        let cr = LegacyDEAL128::with_key(0x0123_4567_89ab_cdef_fedc_ba98_7654_3210);
+        assert_eq!(
+            cr.encrypt(0x0011_2233_4455_6677_8899_aabb_ccdd_eeff),
+            0x4180_39ac_bff1_cb55_9160_a156_0e74_ea84
+        );
+        assert_eq!(
+            cr.decrypt(0x4180_39ac_bff1_cb55_9160_a156_0e74_ea84),
+            0x0011_2233_4455_6677_8899_aabb_ccdd_eeff
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        // Knudsen's equations written out
+        let k = [
+            0x0123_4567_89ab_cdef,
+            0xfedc_ba98_7654_3210,
+            0x0011_2233_4455_6677,
+            0x8899_aabb_ccdd_eeff,
+        ];
+        let e = |x| DES::new(SCHEDULE_KEY).encrypt(x);
+        let cr = DEAL128::with_key(((k[0] as u128) << 64) | k[1] as u128);
+        let mut rk = [e(k[0]), 0, 0, 0, 0, 0];
+        rk[1] = e(k[1] ^ rk[0]);
+        rk[2] = e(k[0] ^ get_bit_at(1) ^ rk[1]);
+        rk[3] = e(k[1] ^ get_bit_at(2) ^ rk[2]);
+        rk[4] = e(k[0] ^ get_bit_at(4) ^ rk[3]);
+        rk[5] = e(k[1] ^ get_bit_at(8) ^ rk[4]);
+        assert_eq!((cr.rounds, &cr.round_keys[..6]), (6, &rk[..]));
+
+        let cr = DEAL192::with_key([k[0], k[1], k[2]]);
+        let mut rk = [e(k[0]), 0, 0, 0, 0, 0];
+        rk[1] = e(k[1] ^ rk[0]);
+        rk[2] = e(k[2] ^ rk[1]);
+        rk[3] = e(k[0] ^ get_bit_at(1) ^ rk[2]);
+        rk[4] = e(k[1] ^ get_bit_at(2) ^ rk[3]);
+        rk[5] = e(k[2] ^ get_bit_at(4) ^ rk[4]);
+        assert_eq!((cr.rounds, &cr.round_keys[..6]), (6, &rk[..]));
+        assert_eq!(cr.encrypt(0), 0x764f_dd1e_0544_4818_bb1f_9966_1c35_df3c);
+
+        let cr = DEAL256::with_key(k);
+        let mut rk = [e(k[0]), 0, 0, 0, 0, 0, 0, 0];
+        for i in 1..4 {
+            rk[i] = e(k[i] ^ rk[i - 1]);
+        }
+        for i in 4..8 {
+            rk[i] = e(k[i - 4] ^ get_bit_at(1 << (i - 4)) ^ rk[i - 1]);
+        }
+        assert_eq!((cr.rounds, cr.round_keys), (8, rk));
+        assert_eq!(cr.encrypt(0), 0x1648_a680_891f_4c43_5122_d8e8_7f76_19b3);
+    }
+
+    #[test]
+    fn test_weak_round_keys() {
+        assert!(!DEAL128::with_key(DEAL128::generate_key(&mut OsRng)).has_weak_round_key());
+        assert!(!DEAL256::with_key(DEAL256::generate_key(&mut OsRng)).has_weak_round_key());
+
+        // the first round key is the first half of the key, run through DES
+        let weak = DES::new(0x0123_4567_89ab_cdef).decrypt(0x0101_0101_0101_0101);
+        assert!(DEAL128::with_key((weak as u128) << 64).has_weak_round_key());
+    }
 }
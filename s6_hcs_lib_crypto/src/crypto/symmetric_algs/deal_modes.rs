@@ -1,9 +1,18 @@
-use crate::crypto::symmetric_algs::DEAL128;
-use rand::random;
+use crate::crypto::error::CryptoError;
+use crate::crypto::mac::ct_eq;
+use crate::crypto::padding::{PaddingAlgorithm, PaddingPKSC7};
+use crate::crypto::progress::{CancelToken, Progress, Tracker};
+use crate::crypto::rng::{SecureRng, SeedableRng, StdRng};
+use crate::crypto::symmetric_algs::{
+    stream::{self, StreamDecryptor, StreamEncryptor},
+    Block, BlockCipher, LegacyDEAL128,
+};
 use rayon::prelude::*;
-use std::hash::{Hash, Hasher};
-use std::sync::mpsc::Sender;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum DEALMode {
     ECB,
     CBC,
@@ -11,312 +20,1106 @@ pub enum DEALMode {
     OFB,
     CTR,
     RD,
+    // RD with an encrypted FNV-1a checksum of the plaintext as tag. That
+    // catches corruption, not forgery; only GCM and ChaCha20-Poly1305
+    // authenticate the data.
     RDH,
+    CTS,
 }
 
-impl DEALMode {
-    pub fn encrypt(
-        &self,
-        input: Vec<u128>,
-        key: u128,
-        tx: Option<Sender<Option<()>>>,
-    ) -> Vec<u128> {
-        let iv = random::<u128>() >> 1;
-        let deal = DEAL128::with_key(key);
-
-        let out = match self {
-            DEALMode::ECB => {
-                let mut output: Vec<(usize, u128)> = input
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.encrypt(b))
-                    })
-                    .collect();
-                output.par_sort_unstable_by_key(|(i, _)| i.clone());
-                output.iter().map(|(_, b)| b.clone()).collect()
+// FNV-1a, unlike DefaultHasher its output does not change between toolchains
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// blocks per segment in parallel CBC and CFB decryption
+const SEGMENT_BLOCKS: usize = 1024;
+
+// blocks between progress reports and cancellation checks
+const STEP_BLOCKS: usize = 64 * SEGMENT_BLOCKS;
+
+fn counter<B: Block>(i: usize) -> B {
+    B::from_u64(i as u64)
+}
+
+fn to_blocks<B: Block>(bytes: &[u8]) -> Vec<B> {
+    bytes.chunks(B::BYTES).map(B::from_bytes).collect()
+}
+
+fn from_blocks<B: Block>(blocks: &[B]) -> Vec<u8> {
+    blocks.iter().flat_map(|b| b.to_bytes()).collect()
+}
+
+struct ModeCore<C: BlockCipher> {
+    mode: DEALMode,
+    cipher: C,
+    iv: C::Block,
+    delta: C::Block,
+    last: C::Block,
+    index: usize,
+    hash: u64,
+}
+
+impl<C: BlockCipher> ModeCore<C> {
+    fn new(mode: DEALMode, cipher: C, iv: C::Block) -> Self {
+        Self {
+            mode,
+            cipher,
+            iv,
+            delta: iv.low_half(),
+            last: iv,
+            index: 0,
+            hash: FNV_OFFSET,
+        }
+    }
+
+    fn from_header(mode: DEALMode, cipher: C, header: &[C::Block]) -> Self {
+        let iv = match mode {
+            DEALMode::ECB => C::Block::from_u64(0),
+            DEALMode::RD | DEALMode::RDH => cipher.decrypt(header[0]),
+            _ => header[0],
+        };
+        Self::new(mode, cipher, iv)
+    }
+
+    fn header(&self) -> Vec<C::Block> {
+        match self.mode {
+            DEALMode::ECB => vec![],
+            DEALMode::RD | DEALMode::RDH => vec![self.cipher.encrypt(self.iv)],
+            _ => vec![self.iv],
+        }
+    }
+
+    fn tag(&self) -> Vec<C::Block> {
+        match self.mode {
+            DEALMode::RDH => vec![self
+                .cipher
+                .encrypt(C::Block::from_u64(self.hash).xor(self.iv))],
+            _ => vec![],
+        }
+    }
+
+    fn check_tag(&self, tag: &[C::Block]) -> bool {
+        match self.mode {
+            DEALMode::RDH => {
+                let expected = C::Block::from_u64(self.hash).to_bytes();
+                tag.len() == 1
+                    && ct_eq(
+                        &self.cipher.decrypt(tag[0]).xor(self.iv).to_bytes(),
+                        &expected,
+                    )
             }
+            _ => tag.is_empty(),
+        }
+    }
 
-            DEALMode::CBC => {
-                let mut output = Vec::new();
-                output.push(iv);
-                for (i, b) in input.iter().enumerate() {
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
-                    output.push(deal.encrypt(b ^ output[i]));
+    fn offset(&self, i: usize) -> C::Block {
+        self.iv
+            .wrapping_add(self.delta.wrapping_mul(counter(self.index + i + 1)))
+    }
+
+    fn update_hash(&mut self, data: &[u8]) {
+        for byte in data {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    // data.len() has to be a multiple of the block size
+    fn encrypt_blocks(&mut self, data: &mut [u8]) {
+        let size = C::Block::BYTES;
+        match self.mode {
+            DEALMode::ECB => data.par_chunks_mut(size).for_each(|c| {
+                self.cipher.encrypt(C::Block::from_bytes(c)).write_bytes(c);
+            }),
+
+            DEALMode::CBC | DEALMode::CTS => {
+                for c in data.chunks_mut(size) {
+                    self.last = self.cipher.encrypt(C::Block::from_bytes(c).xor(self.last));
+                    self.last.write_bytes(c);
                 }
-                output
             }
 
             DEALMode::CFB => {
-                let mut output = Vec::new();
-                output.push(iv);
-                for (i, b) in input.iter().enumerate() {
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
-                    output.push(deal.encrypt(output[i]) ^ b);
+                for c in data.chunks_mut(size) {
+                    self.last = self.cipher.encrypt(self.last).xor(C::Block::from_bytes(c));
+                    self.last.write_bytes(c);
                 }
-                output
             }
 
             DEALMode::OFB => {
-                let mut output = vec![0; input.len() + 1];
-                output[0] = iv;
-                let mut last = deal.encrypt(output[0]);
-                for i in 0..input.len() {
-                    output[i + 1] = input[i] ^ last;
-                    last = deal.encrypt(last);
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
+                for c in data.chunks_mut(size) {
+                    self.last = self.cipher.encrypt(self.last);
+                    C::Block::from_bytes(c).xor(self.last).write_bytes(c);
                 }
-                output
             }
 
-            DEALMode::CTR => {
-                let mut output = vec![0; input.len() + 1];
-                output[0] = iv;
-                for i in 0..input.len() {
-                    output[i + 1] = input[i] ^ deal.encrypt(iv ^ (i as u128 + 1));
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
+            DEALMode::CTR => data.par_chunks_mut(size).enumerate().for_each(|(i, c)| {
+                let ctr = self.iv.wrapping_add(counter(self.index + i));
+                C::Block::from_bytes(c)
+                    .xor(self.cipher.encrypt(ctr))
+                    .write_bytes(c);
+            }),
+
+            DEALMode::RD | DEALMode::RDH => {
+                if self.mode == DEALMode::RDH {
+                    self.update_hash(data);
                 }
-                output
+                data.par_chunks_mut(size).enumerate().for_each(|(i, c)| {
+                    let b = C::Block::from_bytes(c).xor(self.offset(i));
+                    self.cipher.encrypt(b).write_bytes(c);
+                });
             }
+        }
+        self.index += data.len() / size;
+    }
 
-            DEALMode::RD => {
-                let delta = iv as u64 as u128;
-                let mut enc_header = vec![deal.encrypt(iv)];
-                let mut output: Vec<(usize, u128)> = input
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.encrypt(b ^ (iv + delta * (*i as u128 + 1))))
-                    })
-                    .collect();
-                output.par_sort_unstable_by_key(|(i, _)| i.clone());
-                let mut output = output.iter().map(|(_, b)| b.clone()).collect();
-                enc_header.append(&mut output);
-                enc_header
-            }
+    fn decrypt_blocks(&mut self, data: &mut [u8]) {
+        let size = C::Block::BYTES;
+        match self.mode {
+            DEALMode::ECB => data.par_chunks_mut(size).for_each(|c| {
+                self.cipher.decrypt(C::Block::from_bytes(c)).write_bytes(c);
+            }),
 
-            DEALMode::RDH => {
-                let hash = {
-                    let mut hash = std::collections::hash_map::DefaultHasher::new();
-                    input.hash(&mut hash);
-                    hash.finish() as u128
-                };
-
-                let delta = iv as u64 as u128;
-                let mut enc_header = vec![deal.encrypt(iv), deal.encrypt(hash ^ iv)];
-
-                let mut output: Vec<(usize, u128)> = input
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.encrypt(b ^ (iv + delta * (*i as u128 + 1))))
-                    })
-                    .collect();
-                output.par_sort_unstable_by_key(|(i, _)| i.clone());
-                let mut output = output.iter().map(|(_, b)| b.clone()).collect();
-                enc_header.append(&mut output);
-                enc_header
-            }
-        };
-        if let Some(tx) = &tx {
-            tx.send(None).unwrap_or_default()
-        };
-        out
-    }
+            DEALMode::CBC | DEALMode::CTS | DEALMode::CFB => self.decrypt_chained(data),
 
-    pub fn decrypt(
-        &self,
-        input: Vec<u128>,
-        key: u128,
-        tx: Option<Sender<Option<()>>>,
-    ) -> Result<Vec<u128>, ()> {
-        let deal = DEAL128::with_key(key);
-
-        let out = match self {
-            DEALMode::ECB => {
-                let mut output: Vec<(usize, u128)> = input
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.decrypt(b))
-                    })
-                    .collect();
-                output.par_sort_unstable_by_key(|(i, _)| i.clone());
-                Ok(output.iter().map(|(_, b)| b.clone()).collect())
+            DEALMode::OFB | DEALMode::CTR => {
+                self.encrypt_blocks(data);
+                return;
             }
 
-            DEALMode::CBC => {
-                let mut output = Vec::new();
-                for (i, &b) in input[1..].iter().enumerate() {
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
-                    output.push(deal.decrypt(b) ^ input[i]);
+            DEALMode::RD | DEALMode::RDH => {
+                data.par_chunks_mut(size).enumerate().for_each(|(i, c)| {
+                    let b = self.cipher.decrypt(C::Block::from_bytes(c));
+                    b.xor(self.offset(i)).write_bytes(c);
+                });
+                if self.mode == DEALMode::RDH {
+                    self.update_hash(data);
                 }
-                Ok(output)
             }
+        }
+        self.index += data.len() / size;
+    }
 
-            DEALMode::CFB => {
-                let mut output = Vec::new();
-                for (i, b) in input[1..].iter().enumerate() {
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
-                    };
-                    output.push(deal.encrypt(input[i]) ^ b);
-                }
-                Ok(output)
-            }
+    // Decryption in CBC and CFB only needs the previous ciphertext block.
+    // Segments are decrypted in parallel, each one back to front, so that
+    // block is still intact when it is needed.
+    fn decrypt_chained(&mut self, data: &mut [u8]) {
+        let size = C::Block::BYTES;
+        let segment = SEGMENT_BLOCKS * size;
+        let mut prev = vec![self.last];
+        prev.extend(
+            data.chunks(segment)
+                .map(|s| C::Block::from_bytes(&s[s.len() - size..])),
+        );
+        self.last = *prev.last().unwrap();
 
-            DEALMode::OFB => {
-                let mut output = vec![0; input.len() - 1];
-                let mut last = deal.encrypt(input[0]);
-                for i in 1..input.len() {
-                    output[i - 1] = input[i] ^ last;
-                    last = deal.encrypt(last);
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
+        let (mode, cipher) = (self.mode, &self.cipher);
+        data.par_chunks_mut(segment)
+            .zip(prev)
+            .for_each(|(s, first)| {
+                for i in (0..s.len() / size).rev() {
+                    let p = match i {
+                        0 => first,
+                        _ => C::Block::from_bytes(&s[(i - 1) * size..i * size]),
                     };
-                }
-                Ok(output)
-            }
-
-            DEALMode::CTR => {
-                let mut output = vec![0; input.len() - 1];
-                let iv = input[0];
-                for i in 1..input.len() {
-                    output[i - 1] = input[i] ^ deal.encrypt(iv ^ (i as u128));
-                    if let Some(tx) = &tx {
-                        tx.send(Some(())).unwrap_or_default()
+                    let c = &mut s[i * size..(i + 1) * size];
+                    let out = match mode {
+                        DEALMode::CFB => cipher.encrypt(p).xor(C::Block::from_bytes(c)),
+                        _ => cipher.decrypt(C::Block::from_bytes(c)).xor(p),
                     };
+                    out.write_bytes(c);
                 }
-                Ok(output)
-            }
+            });
+    }
+
+    // keystream for a trailing partial block; CTS uses it for inputs shorter
+    // than a block, there is nothing to steal from
+    fn keystream(&self) -> C::Block {
+        match self.mode {
+            DEALMode::CTR => self
+                .cipher
+                .encrypt(self.iv.wrapping_add(counter(self.index))),
+            _ => self.cipher.encrypt(self.last),
+        }
+    }
+
+    // how many buffered bytes can be processed before the input is known to end
+    fn ready(&self, len: usize) -> usize {
+        let size = C::Block::BYTES;
+        match self.mode {
+            DEALMode::CTS => len.saturating_sub(size + 1) / size * size,
+            _ => len / size * size,
+        }
+    }
+
+    // bytes the CTS swap works on at the end of the input
+    fn stolen(&self, len: usize) -> usize {
+        let size = C::Block::BYTES;
+        match self.mode {
+            DEALMode::CTS if len > size => size + (len - 1) % size + 1,
+            _ => len % size,
+        }
+    }
+
+    fn xor_keystream(&self, data: &mut [u8]) {
+        let ks = self.keystream().to_bytes();
+        data.iter_mut().zip(ks).for_each(|(b, k)| *b ^= k);
+    }
 
-            DEALMode::RD => {
-                let deal = DEAL128::with_key(key);
-                let iv = deal.decrypt(input[0]);
-                let delta = iv as u64 as u128;
-                let mut dec: Vec<(usize, u128)> = input[1..]
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.decrypt(b) ^ (iv + delta * (*i as u128 + 1)))
-                    })
-                    .collect();
-                dec.par_sort_unstable_by_key(|(i, _)| i.clone());
-                let dec: Vec<u128> = dec.iter().map(|(_, b)| b.clone()).collect();
-                Ok(dec)
+    fn encrypt_final(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        let size = C::Block::BYTES;
+        let n = data.len() - self.stolen(data.len());
+        let (body, tail) = data.split_at_mut(n);
+        self.encrypt_blocks(body);
+
+        if self.mode == DEALMode::CTS && tail.len() > size {
+            // CS3: CBC over the zero-filled last block, swap the last two
+            // blocks and cut the second one to the length of the plaintext
+            let d = tail.len() - size;
+            let mut pair = tail.to_vec();
+            pair.resize(2 * size, 0);
+            self.encrypt_blocks(&mut pair);
+            tail[..size].copy_from_slice(&pair[size..]);
+            tail[size..].copy_from_slice(&pair[..d]);
+        } else if !tail.is_empty() {
+            if self.mode.needs_padding() {
+                return Err(CryptoError::InvalidLength);
             }
+            self.xor_keystream(tail);
+        }
+        Ok(())
+    }
 
-            DEALMode::RDH => {
-                let deal = DEAL128::with_key(key);
-                let iv = deal.decrypt(input[0]);
-                let delta = iv as u64 as u128;
-                let in_hash = deal.decrypt(input[1]) ^ iv;
-
-                let mut dec: Vec<(usize, u128)> = input[2..]
-                    .iter()
-                    .enumerate()
-                    .collect::<Vec<(usize, &u128)>>()
-                    .par_iter()
-                    .map(|(i, &b)| {
-                        if let Some(tx) = &tx {
-                            tx.send(Some(())).unwrap_or_default()
-                        };
-                        (i.clone(), deal.decrypt(b) ^ (iv + delta * (*i as u128 + 1)))
-                    })
-                    .collect();
-                dec.par_sort_unstable_by_key(|(i, _)| i.clone());
-                let dec: Vec<u128> = dec.iter().map(|(_, b)| b.clone()).collect();
-
-                let out_hash = {
-                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                    dec.hash(&mut hasher);
-                    hasher.finish() as u128
-                };
-
-                if out_hash == in_hash {
-                    Ok(dec)
-                } else {
-                    Err(())
-                }
+    fn decrypt_final(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        let size = C::Block::BYTES;
+        let n = data.len() - self.stolen(data.len());
+        let (body, tail) = data.split_at_mut(n);
+        self.decrypt_blocks(body);
+
+        if self.mode == DEALMode::CTS && tail.len() > size {
+            let d = tail.len() - size;
+            let z = self
+                .cipher
+                .decrypt(C::Block::from_bytes(&tail[..size]))
+                .to_bytes();
+            let last: Vec<u8> = z.iter().zip(&tail[size..]).map(|(a, b)| a ^ b).collect();
+            let mut prev = [&tail[size..], &z[d..]].concat();
+            self.decrypt_blocks(&mut prev);
+            tail[..size].copy_from_slice(&prev);
+            tail[size..].copy_from_slice(&last);
+        } else if !tail.is_empty() {
+            if self.mode.needs_padding() {
+                return Err(CryptoError::InvalidLength);
             }
-        };
-        if let Some(tx) = &tx {
-            tx.send(None).unwrap_or_default()
-        };
-        out
+            self.xor_keystream(tail);
+        }
+        Ok(())
+    }
+
+    // the *_final functions in steps, reporting progress in between
+    fn encrypt_tracked(&mut self, data: &mut [u8], tracker: &Tracker) -> Result<(), CryptoError> {
+        let n = data.len() - self.stolen(data.len());
+        let (body, tail) = data.split_at_mut(n);
+        for step in body.chunks_mut(STEP_BLOCKS * C::Block::BYTES) {
+            tracker.check()?;
+            self.encrypt_blocks(step);
+            tracker.advance(step.len() as u64)?;
+        }
+        self.encrypt_final(tail)?;
+        tracker.advance(tail.len() as u64)
+    }
+
+    fn decrypt_tracked(&mut self, data: &mut [u8], tracker: &Tracker) -> Result<(), CryptoError> {
+        let n = data.len() - self.stolen(data.len());
+        let (body, tail) = data.split_at_mut(n);
+        for step in body.chunks_mut(STEP_BLOCKS * C::Block::BYTES) {
+            tracker.check()?;
+            self.decrypt_blocks(step);
+            tracker.advance(step.len() as u64)?;
+        }
+        self.decrypt_final(tail)?;
+        tracker.advance(tail.len() as u64)
+    }
+}
+
+pub struct Encryptor<C: BlockCipher, P: PaddingAlgorithm> {
+    core: ModeCore<C>,
+    padding: P,
+    buffer: Vec<u8>,
+    // seeded from the caller's rng, for padding filled at finalize
+    rng: StdRng,
+}
+
+pub struct Decryptor<C: BlockCipher, P: PaddingAlgorithm> {
+    core: ModeCore<C>,
+    padding: P,
+    buffer: Vec<u8>,
+}
+
+impl<C: BlockCipher, P: PaddingAlgorithm> StreamEncryptor for Encryptor<C, P> {
+    fn iv(&self) -> Vec<u8> {
+        from_blocks(&self.core.header())
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.buffer.extend_from_slice(input);
+        let n = self.core.ready(self.buffer.len());
+        let mut out: Vec<u8> = self.buffer.drain(..n).collect();
+        self.core.encrypt_blocks(&mut out);
+        Ok(out)
+    }
+
+    fn finalize(&mut self) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        self.padding.apply_padding(&mut self.buffer, &mut self.rng);
+        let mut tail = std::mem::take(&mut self.buffer);
+        self.core.encrypt_final(&mut tail)?;
+        Ok((tail, from_blocks(&self.core.tag())))
+    }
+}
+
+impl<C: BlockCipher, P: PaddingAlgorithm> StreamDecryptor for Decryptor<C, P> {
+    fn tag_size(&self) -> usize {
+        self.core.tag().len() * C::Block::BYTES
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.buffer.extend_from_slice(input);
+        // the last block may hold padding, so it waits for finalize
+        let n = self.core.ready(self.buffer.len().saturating_sub(1));
+        let mut out: Vec<u8> = self.buffer.drain(..n).collect();
+        self.core.decrypt_blocks(&mut out);
+        Ok(out)
+    }
+
+    fn finalize(&mut self, tag: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if !tag.len().is_multiple_of(C::Block::BYTES) {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mut tail = std::mem::take(&mut self.buffer);
+        self.core.decrypt_final(&mut tail)?;
+        if !self.core.check_tag(&to_blocks(tag)) {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        self.padding.remove_padding(&mut tail)?;
+        Ok(tail)
+    }
+}
+
+// RDH as files were written before the container: E(iv) | E(hash ^ iv) |
+// blocks, with DEAL-128 on the legacy DES schedule, PKCS#7 padding and the
+// DefaultHasher of the padded plaintext blocks as hash. Only kept to read
+// those files.
+pub struct LegacyRDHDecryptor {
+    core: ModeCore<LegacyDEAL128>,
+    hash: Option<u128>,
+    hasher: DefaultHasher,
+    buffer: Vec<u8>,
+}
+
+impl LegacyRDHDecryptor {
+    // blocks is the number of plaintext blocks, the hash starts with it
+    pub fn new(key: u128, iv: &[u8], blocks: usize) -> Result<Self, CryptoError> {
+        if iv.len() != u128::BYTES {
+            return Err(CryptoError::InvalidLength);
+        }
+        let cipher = LegacyDEAL128::with_key(key);
+        let mut hasher = DefaultHasher::new();
+        hasher.write_usize(blocks);
+        Ok(Self {
+            core: ModeCore::from_header(DEALMode::RD, cipher, &to_blocks(iv)),
+            hash: None,
+            hasher,
+            buffer: Vec::new(),
+        })
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) {
+        self.core.decrypt_blocks(data);
+        for block in to_blocks::<u128>(data) {
+            self.hasher.write(&block.to_ne_bytes());
+        }
+    }
+}
+
+impl StreamDecryptor for LegacyRDHDecryptor {
+    fn tag_size(&self) -> usize {
+        0
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        self.buffer.extend_from_slice(input);
+        if self.hash.is_none() && self.buffer.len() >= u128::BYTES {
+            let block = self.buffer.drain(..u128::BYTES).collect::<Vec<u8>>();
+            let core = &self.core;
+            self.hash = Some(core.cipher.decrypt(u128::from_bytes(&block)) ^ core.iv);
+        }
+        // the last block holds the padding, so it waits for finalize
+        let n = self.core.ready(self.buffer.len().saturating_sub(1));
+        let mut out: Vec<u8> = self.buffer.drain(..n).collect();
+        self.decrypt(&mut out);
+        Ok(out)
+    }
+
+    fn finalize(&mut self, tag: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let hash = self.hash.ok_or(CryptoError::InvalidLength)?;
+        if !tag.is_empty() || !self.buffer.len().is_multiple_of(u128::BYTES) {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mut tail = std::mem::take(&mut self.buffer);
+        self.decrypt(&mut tail);
+        if self.hasher.finish() as u128 != hash {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        PaddingPKSC7::with_block_size(16).remove_padding(&mut tail)?;
+        Ok(tail)
+    }
+}
+
+impl DEALMode {
+    fn header_len(&self) -> usize {
+        match self {
+            DEALMode::ECB => 0,
+            _ => 1,
+        }
+    }
+
+    fn tag_len(&self) -> usize {
+        match self {
+            DEALMode::RDH => 1,
+            _ => 0,
+        }
+    }
+
+    // CFB, OFB and CTR handle a partial last block, CTS steals from the one before
+    pub fn needs_padding(&self) -> bool {
+        matches!(
+            self,
+            DEALMode::ECB | DEALMode::CBC | DEALMode::RD | DEALMode::RDH
+        )
+    }
+
+    pub fn iv_size<C: BlockCipher>(&self) -> usize {
+        self.header_len() * C::Block::BYTES
+    }
+
+    pub fn tag_size<C: BlockCipher>(&self) -> usize {
+        self.tag_len() * C::Block::BYTES
+    }
+
+    // Encrypts data where it is and returns the iv and tag. Modes that need
+    // padding expect it to be applied already.
+    pub fn encrypt_in_place<C: BlockCipher>(
+        &self,
+        key: C::Key,
+        data: &mut [u8],
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        let iv = C::Block::random(rng);
+        self.encrypt_in_place_with_iv::<C>(key, iv, data, progress, cancel)
+    }
+
+    // Same with the iv picked by the caller, for known-answer tests. ECB
+    // ignores it, RD and RDH store it encrypted. Never reuse an iv with a key.
+    pub fn encrypt_in_place_with_iv<C: BlockCipher>(
+        &self,
+        key: C::Key,
+        iv: C::Block,
+        data: &mut [u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        if self.needs_padding() && !data.len().is_multiple_of(C::Block::BYTES) {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mut core = ModeCore::new(*self, C::with_key(key), iv);
+        core.encrypt_tracked(data, &Tracker::new(progress, cancel, data.len() as u64))?;
+        Ok((from_blocks(&core.header()), from_blocks(&core.tag())))
+    }
+
+    pub fn decrypt_in_place<C: BlockCipher>(
+        &self,
+        key: C::Key,
+        iv: &[u8],
+        data: &mut [u8],
+        tag: &[u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), CryptoError> {
+        if iv.len() != self.iv_size::<C>() || tag.len() != self.tag_size::<C>() {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mut core = ModeCore::from_header(*self, C::with_key(key), &to_blocks(iv));
+        core.decrypt_tracked(data, &Tracker::new(progress, cancel, data.len() as u64))?;
+        match core.check_tag(&to_blocks(tag)) {
+            true => Ok(()),
+            false => Err(CryptoError::AuthenticationFailed),
+        }
+    }
+
+    pub fn encrypt<C: BlockCipher>(
+        &self,
+        input: Vec<C::Block>,
+        key: C::Key,
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<C::Block>, CryptoError> {
+        let iv = C::Block::random(rng);
+        self.encrypt_with_iv::<C>(input, key, iv, progress, cancel)
+    }
+
+    pub fn encrypt_with_iv<C: BlockCipher>(
+        &self,
+        input: Vec<C::Block>,
+        key: C::Key,
+        iv: C::Block,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<C::Block>, CryptoError> {
+        let mut data = from_blocks(&input);
+        let (iv, tag) = self.encrypt_in_place_with_iv::<C>(key, iv, &mut data, progress, cancel)?;
+        Ok([to_blocks(&iv), to_blocks(&data), to_blocks(&tag)].concat())
+    }
+
+    pub fn decrypt<C: BlockCipher>(
+        &self,
+        input: Vec<C::Block>,
+        key: C::Key,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<C::Block>, CryptoError> {
+        let (header_len, tag_len) = (self.header_len(), self.tag_len());
+        if input.len() < header_len + tag_len {
+            return Err(CryptoError::InvalidLength);
+        }
+        let (iv, rest) = input.split_at(header_len);
+        let (body, tag) = rest.split_at(rest.len() - tag_len);
+        let mut data = from_blocks(body);
+        self.decrypt_in_place::<C>(
+            key,
+            &from_blocks(iv),
+            &mut data,
+            &from_blocks(tag),
+            progress,
+            cancel,
+        )?;
+        Ok(to_blocks(&data))
+    }
+
+    // Output is the iv followed by exactly input.len() bytes
+    pub fn encrypt_bytes<C: BlockCipher>(
+        &self,
+        input: &[u8],
+        key: C::Key,
+        rng: &mut dyn SecureRng,
+    ) -> Result<Vec<u8>, CryptoError> {
+        if self.needs_padding() {
+            return Err(CryptoError::Unsupported);
+        }
+        let mut data = input.to_vec();
+        let (iv, _) = self.encrypt_in_place::<C>(key, &mut data, rng, &(), &CancelToken::new())?;
+        Ok([iv, data].concat())
+    }
+
+    pub fn decrypt_bytes<C: BlockCipher>(
+        &self,
+        input: &[u8],
+        key: C::Key,
+    ) -> Result<Vec<u8>, CryptoError> {
+        let iv_size = self.iv_size::<C>();
+        if self.needs_padding() {
+            return Err(CryptoError::Unsupported);
+        }
+        if input.len() < iv_size {
+            return Err(CryptoError::InvalidLength);
+        }
+        let mut data = input[iv_size..].to_vec();
+        self.decrypt_in_place::<C>(
+            key,
+            &input[..iv_size],
+            &mut data,
+            &[],
+            &(),
+            &CancelToken::new(),
+        )?;
+        Ok(data)
+    }
+
+    pub fn encryptor<C: BlockCipher, P: PaddingAlgorithm>(
+        &self,
+        key: C::Key,
+        rng: &mut dyn SecureRng,
+    ) -> Encryptor<C, P> {
+        let core = ModeCore::new(*self, C::with_key(key), C::Block::random(rng));
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        Encryptor {
+            core,
+            padding: P::with_block_size(C::Block::BYTES as u8),
+            buffer: Vec::new(),
+            rng: StdRng::from_seed(seed),
+        }
+    }
+
+    pub fn decryptor<C: BlockCipher, P: PaddingAlgorithm>(
+        &self,
+        key: C::Key,
+        iv: &[u8],
+    ) -> Result<Decryptor<C, P>, CryptoError> {
+        if iv.len() != self.iv_size::<C>() {
+            return Err(CryptoError::InvalidLength);
+        }
+        Ok(Decryptor {
+            core: ModeCore::from_header(*self, C::with_key(key), &to_blocks(iv)),
+            padding: P::with_block_size(C::Block::BYTES as u8),
+            buffer: Vec::new(),
+        })
+    }
+
+    pub fn decrypt_stream<C: BlockCipher, P: PaddingAlgorithm>(
+        &self,
+        key: C::Key,
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        len: u64,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> io::Result<()> {
+        let mut iv = vec![0; self.iv_size::<C>()];
+        reader.read_exact(&mut iv)?;
+        let mut decryptor = self.decryptor::<C, P>(key, &iv)?;
+        stream::decrypt_stream(&mut decryptor, reader, writer, len, progress, cancel)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::padding::{PaddingNone, PaddingPKSC7};
+    use crate::crypto::rng::{seeded_rng, OsRng};
+    use crate::crypto::symmetric_algs::{
+        Rijndael, Serpent128, Twofish192, AES128, AES256, DEAL128, DES, MARS256, RC6128,
+    };
+    use crate::crypto::test_util::{hex, legacy_rdh};
+    use rand::random;
+
+    const MODES: [DEALMode; 8] = [
+        DEALMode::ECB,
+        DEALMode::CBC,
+        DEALMode::CFB,
+        DEALMode::OFB,
+        DEALMode::CTR,
+        DEALMode::RD,
+        DEALMode::RDH,
+        DEALMode::CTS,
+    ];
+
+    fn test_modes<C: BlockCipher>() {
+        let mut rng = seeded_rng(1);
+        let data: Vec<C::Block> = (0..1024).map(|_| C::Block::random(&mut rng)).collect();
+        let key = C::generate_key(&mut rng);
+
+        for mode in MODES {
+            let enc = mode
+                .encrypt::<C>(data.clone(), key, &mut rng, &(), &CancelToken::new())
+                .unwrap();
+            let new_data = mode
+                .decrypt::<C>(enc, key, &(), &CancelToken::new())
+                .unwrap();
+            assert_eq!(new_data, data);
+        }
+    }
 
     #[test]
     fn test_all() {
-        let data: Vec<u128> = (0..1024).map(|_| random()).collect();
-        let key = DEAL128::generate_key();
+        test_modes::<DES>();
+        test_modes::<DEAL128>();
+        test_modes::<AES256>();
+        test_modes::<Rijndael<[u64; 4], [u64; 3]>>();
+        test_modes::<Serpent128>();
+        test_modes::<Twofish192>();
+        test_modes::<RC6128>();
+        test_modes::<MARS256>();
+    }
+
+    #[test]
+    fn test_stream() {
+        let key = DEAL128::generate_key(&mut OsRng);
+        for len in [0, 15, 16, 1000, stream::CHUNK_SIZE + 7] {
+            let data: Vec<u8> = (0..len).map(|_| random()).collect();
+            for mode in MODES {
+                let mut enc = Vec::new();
+                let (src, len) = (&mut &data[..], data.len() as u64);
+                stream::encrypt_stream(
+                    &mut mode.encryptor::<DEAL128, PaddingPKSC7>(key, &mut OsRng),
+                    src,
+                    &mut enc,
+                    len,
+                    &(),
+                    &CancelToken::new(),
+                )
+                .unwrap();
+
+                // streamed output is the padded batch format
+                let mut padded = data.clone();
+                PaddingPKSC7::with_block_size(16).apply_padding(&mut padded, &mut OsRng);
+                let batch = mode
+                    .decrypt::<DEAL128>(to_blocks(&enc), key, &(), &CancelToken::new())
+                    .unwrap();
+                assert_eq!(from_blocks(&batch), padded);
+
+                let mut dec = Vec::new();
+                let (src, len) = (&mut &enc[..], enc.len() as u64);
+                mode.decrypt_stream::<DEAL128, PaddingPKSC7>(
+                    key,
+                    src,
+                    &mut dec,
+                    len,
+                    &(),
+                    &CancelToken::new(),
+                )
+                .unwrap();
+                assert_eq!(dec, data);
+            }
+        }
+
+        let mut enc = Vec::new();
+        stream::encrypt_stream(
+            &mut DEALMode::RDH.encryptor::<DEAL128, PaddingPKSC7>(key, &mut OsRng),
+            &mut &[1u8; 100][..],
+            &mut enc,
+            100,
+            &(),
+            &CancelToken::new(),
+        )
+        .unwrap();
+        enc[40] ^= 1;
+        let res = DEALMode::RDH.decrypt_stream::<DEAL128, PaddingPKSC7>(
+            key,
+            &mut &enc[..],
+            &mut Vec::new(),
+            enc.len() as u64,
+            &(),
+            &CancelToken::new(),
+        );
+        assert!(res.is_err());
+
+        // last byte 0 is never valid PKCS7
+        let enc = DEALMode::CBC
+            .encrypt::<DEAL128>(vec![0xff00], key, &mut OsRng, &(), &CancelToken::new())
+            .unwrap();
+        let res = DEALMode::CBC.decrypt_stream::<DEAL128, PaddingPKSC7>(
+            key,
+            &mut &from_blocks(&enc)[..],
+            &mut Vec::new(),
+            16,
+            &(),
+            &CancelToken::new(),
+        );
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn check_modes<C: BlockCipher>(key: C::Key, pt: &[u8], cases: &[(DEALMode, C::Block, &str)]) {
+        for &(mode, iv, expected) in cases {
+            let mut data = pt.to_vec();
+            let cancel = CancelToken::new();
+            let (header, tag) = mode
+                .encrypt_in_place_with_iv::<C>(key, iv, &mut data, &(), &cancel)
+                .unwrap();
+            assert_eq!(data, hex(expected), "{:?}", mode);
+            mode.decrypt_in_place::<C>(key, &header, &mut data, &tag, &(), &cancel)
+                .unwrap();
+            assert_eq!(data, pt);
+        }
+    }
 
-        let enc = DEALMode::ECB.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::ECB.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+    #[test]
+    fn test_known_answers() {
+        // NIST SP 800-38A appendix F, AES-128
+        let pt = hex(concat!(
+            "6bc1bee22e409f96e93d7e117393172a",
+            "ae2d8a571e03ac9c9eb76fac45af8e51",
+            "30c81c46a35ce411e5fbc1191a0a52ef",
+            "f69f2445df4f9b17ad2b417be66c3710"
+        ));
+        let iv = 0x0001_0203_0405_0607_0809_0a0b_0c0d_0e0f;
+        let cases = [
+            (
+                DEALMode::ECB,
+                0,
+                concat!(
+                    "3ad77bb40d7a3660a89ecaf32466ef97",
+                    "f5d3d58503b9699de785895a96fdbaaf",
+                    "43b1cd7f598ece23881b00e3ed030688",
+                    "7b0c785e27e8ad3f8223207104725dd4"
+                ),
+            ),
+            (
+                DEALMode::CBC,
+                iv,
+                concat!(
+                    "7649abac8119b246cee98e9b12e9197d",
+                    "5086cb9b507219ee95db113a917678b2",
+                    "73bed6b8e3c1743b7116e69e22229516",
+                    "3ff1caa1681fac09120eca307586e1a7"
+                ),
+            ),
+            (
+                DEALMode::CFB,
+                iv,
+                concat!(
+                    "3b3fd92eb72dad20333449f8e83cfb4a",
+                    "c8a64537a0b3a93fcde3cdad9f1ce58b",
+                    "26751f67a3cbb140b1808cf187a4f4df",
+                    "c04b05357c5d1c0eeac4c66f9ff7f2e6"
+                ),
+            ),
+            (
+                DEALMode::OFB,
+                iv,
+                concat!(
+                    "3b3fd92eb72dad20333449f8e83cfb4a",
+                    "7789508d16918f03f53c52dac54ed825",
+                    "9740051e9c5fecf64344f7a82260edcc",
+                    "304c6528f659c77866a510d9c1d6ae5e"
+                ),
+            ),
+            // the iv is the first counter block, F.5.1
+            (
+                DEALMode::CTR,
+                0xf0f1_f2f3_f4f5_f6f7_f8f9_fafb_fcfd_feff,
+                concat!(
+                    "874d6191b620e3261bef6864990db6ce",
+                    "9806f66b7970fdff8617187bb9fffdff",
+                    "5ae4df3edbd5d35e5b4f09020db03eab",
+                    "1e031dda2fbe03d1792170a0f3009cee"
+                ),
+            ),
+        ];
+        check_modes::<AES128>(0x2b7e_1516_28ae_d2a6_abf7_1588_09cf_4f3c, &pt, &cases);
+    }
 
-        let enc = DEALMode::CBC.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::CBC.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+    #[test]
+    fn test_deal_known_answers() {
+        // no published DEAL vectors, these were computed apart from this code
+        // with OpenSSL's DES, for the SP 800-38A inputs under the key of the
+        // single block test in deal.rs
+        let pt = hex(concat!(
+            "6bc1bee22e409f96e93d7e117393172a",
+            "ae2d8a571e03ac9c9eb76fac45af8e51",
+            "30c81c46a35ce411e5fbc1191a0a52ef",
+            "f69f2445df4f9b17ad2b417be66c3710"
+        ));
+        let iv = 0x0001_0203_0405_0607_0809_0a0b_0c0d_0e0f;
+        let cases = [
+            (
+                DEALMode::ECB,
+                0,
+                concat!(
+                    "b42945c4c84a1bdbff03abc6090b4210",
+                    "278d11c241322a17912cc0fda84a7f53",
+                    "ecbd61f4a9a4a984407e02771cbaaf9b",
+                    "763c019a0d7735fe2ebae9e2bf2dd8d8"
+                ),
+            ),
+            (
+                DEALMode::CBC,
+                iv,
+                concat!(
+                    "6a5e0c293e308c4a644a496624a7ca43",
+                    "b64fc629e34309b09e91c70775fe09e9",
+                    "f6f0eb3c76562f8037d6c484bc965ec1",
+                    "4cec97828b3a4051d179dce213747349"
+                ),
+            ),
+            (
+                DEALMode::CFB,
+                iv,
+                concat!(
+                    "a4cb221cf8a086eb2eab872da52d194b",
+                    "1dda2d075cc4795fb686dff2789a81c5",
+                    "51f2ef7a5dc2c830896e120a94899e3a",
+                    "0dcb35b067db0cfd907a0f3f7df253a4"
+                ),
+            ),
+            (
+                DEALMode::OFB,
+                iv,
+                concat!(
+                    "a4cb221cf8a086eb2eab872da52d194b",
+                    "90f4e428fc46fa396b0f189506606f7f",
+                    "986fe868e3129317a8f8328f194137d2",
+                    "fbba996f285acdba47d0a991d4fc56f6"
+                ),
+            ),
+            (
+                DEALMode::CTR,
+                iv,
+                concat!(
+                    "a4cb221cf8a086eb2eab872da52d194b",
+                    "578295fda01045c01dccd900a17ceeaf",
+                    "cde68b518f457fe5a1308b6ffbd79ccc",
+                    "c855c4f4f0ac9a5af0edc16bc3db8f7a"
+                ),
+            ),
+        ];
+        let key = 0x0123_4567_89ab_cdef_fedc_ba98_7654_3210;
+        check_modes::<DEAL128>(key, &pt, &cases);
+    }
 
-        let enc = DEALMode::CFB.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::CFB.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+    #[test]
+    fn test_cts() {
+        // RFC 3962 appendix B, AES-128 with a zero IV
+        let pt = hex(concat!(
+            "4920776f756c64206c696b652074686520",
+            "47656e6572616c2047617527732043"
+        ));
+        let cases = [
+            (17, "c6353568f2bf8cb4d8a580362da7ff7f97"),
+            (
+                31,
+                "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5",
+            ),
+            (
+                32,
+                "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584",
+            ),
+        ];
+        let key = 0x6368_6963_6b65_6e20_7465_7269_7961_6b69;
+        for (len, expected) in cases {
+            let mut data = pt[..len].to_vec();
+            let mut core = ModeCore::new(DEALMode::CTS, AES128::with_key(key), 0);
+            assert_eq!(core.encrypt_final(&mut data), Ok(()));
+            assert_eq!(data, hex(expected));
+            let mut core = ModeCore::new(DEALMode::CTS, AES128::with_key(key), 0);
+            assert_eq!(core.decrypt_final(&mut data), Ok(()));
+            assert_eq!(data, pt[..len]);
+        }
+    }
 
-        let enc = DEALMode::OFB.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::OFB.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+    #[test]
+    fn test_bytes() {
+        let key = AES128::generate_key(&mut OsRng);
+        let data: Vec<u8> = (0..200).map(|_| random()).collect();
+        for mode in [DEALMode::CFB, DEALMode::OFB, DEALMode::CTR, DEALMode::CTS] {
+            for len in [0, 1, 15, 16, 17, 31, 32, 33, 200] {
+                let enc = mode
+                    .encrypt_bytes::<AES128>(&data[..len], key, &mut OsRng)
+                    .unwrap();
+                assert_eq!(enc.len(), 16 + len);
+                assert_eq!(
+                    mode.decrypt_bytes::<AES128>(&enc, key),
+                    Ok(data[..len].to_vec())
+                );
 
-        let enc = DEALMode::CTR.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::CTR.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+                // the stream decryptor reads the same format in small pieces
+                let mut decryptor = mode
+                    .decryptor::<AES128, PaddingNone>(key, &enc[..16])
+                    .unwrap();
+                let mut dec = Vec::new();
+                for chunk in enc[16..].chunks(7) {
+                    dec.extend(decryptor.update(chunk).unwrap());
+                }
+                dec.extend(decryptor.finalize(&[]).unwrap());
+                assert_eq!(dec, data[..len]);
+            }
+        }
+        assert!(DEALMode::CBC
+            .encrypt_bytes::<AES128>(&data, key, &mut OsRng)
+            .is_err());
+    }
 
-        let enc = DEALMode::RD.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::RD.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+    #[test]
+    fn test_in_place() {
+        let key = DEAL128::generate_key(&mut OsRng);
+        let data: Vec<u8> = (0..5000 * 16).map(|_| random()).collect();
+        for mode in MODES {
+            let len = if mode.needs_padding() {
+                data.len()
+            } else {
+                data.len() - 5
+            };
+            let mut buf = data[..len].to_vec();
+            let (iv, tag) = mode
+                .encrypt_in_place::<DEAL128>(key, &mut buf, &mut OsRng, &(), &CancelToken::new())
+                .unwrap();
+            assert_ne!(buf, data[..len]);
+            mode.decrypt_in_place::<DEAL128>(key, &iv, &mut buf, &tag, &(), &CancelToken::new())
+                .unwrap();
+            assert_eq!(buf, data[..len]);
+
+            // the segmented CBC and CFB decryption has to match the serial encryptor
+            let mut encryptor = mode.encryptor::<DEAL128, PaddingNone>(key, &mut OsRng);
+            let mut enc = Vec::new();
+            for chunk in data[..len].chunks(1000) {
+                enc.extend(encryptor.update(chunk).unwrap());
+            }
+            let (tail, tag) = encryptor.finalize().unwrap();
+            enc.extend(tail);
+            mode.decrypt_in_place::<DEAL128>(
+                key,
+                &encryptor.iv(),
+                &mut enc,
+                &tag,
+                &(),
+                &CancelToken::new(),
+            )
+            .unwrap();
+            assert_eq!(enc, data[..len]);
+        }
+        assert!(DEALMode::ECB
+            .encrypt_in_place::<DEAL128>(key, &mut [0; 15], &mut OsRng, &(), &CancelToken::new())
+            .is_err());
+
+        // without padding the streamed block modes refuse a partial last block
+        for mode in [DEALMode::ECB, DEALMode::CBC, DEALMode::RD, DEALMode::RDH] {
+            let mut encryptor = mode.encryptor::<DEAL128, PaddingNone>(key, &mut OsRng);
+            encryptor.update(&data[..20]).unwrap();
+            assert_eq!(encryptor.finalize(), Err(CryptoError::InvalidLength));
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let key = DEAL128::generate_key(&mut OsRng);
+        let mut data = vec![0; 3 * STEP_BLOCKS * 16 + 5];
+        let reported = std::sync::Mutex::new(Vec::new());
+        let progress = |done, total| reported.lock().unwrap().push((done, total));
+        let cancel = CancelToken::new();
+        DEALMode::CTR
+            .encrypt_in_place::<DEAL128>(key, &mut data, &mut OsRng, &progress, &cancel)
+            .unwrap();
+        let total = data.len() as u64;
+        assert_eq!(reported.lock().unwrap().last(), Some(&(total, total)));
+
+        // cancelled after the first step
+        let progress = |_, _| cancel.cancel();
+        let res = DEALMode::CTR
+            .encrypt_in_place::<DEAL128>(key, &mut data, &mut OsRng, &progress, &cancel);
+        assert_eq!(res, Err(CryptoError::Cancelled));
+    }
+
+    #[test]
+    fn test_legacy_rdh() {
+        let key = random();
+        let data: Vec<u8> = (0..1000).map(|_| random()).collect();
+        for len in [0, 15, 16, 17, 1000] {
+            let mut enc = legacy_rdh(key, random::<u128>() >> 1, &data[..len]);
+            let blocks = enc.len() / 16 - 2;
+            let mut dec = LegacyRDHDecryptor::new(key, &enc[..16], blocks).unwrap();
+            let mut out = Vec::new();
+            for chunk in enc[16..].chunks(7) {
+                out.extend(dec.update(chunk).unwrap());
+            }
+            out.extend(dec.finalize(&[]).unwrap());
+            assert_eq!(out, data[..len]);
 
-        let enc = DEALMode::RDH.encrypt(data.clone(), key, None);
-        let new_data = DEALMode::RDH.decrypt(enc, key, None).unwrap();
-        assert_eq!(new_data, data);
+            enc[40] ^= 1;
+            let mut dec = LegacyRDHDecryptor::new(key, &enc[..16], blocks).unwrap();
+            dec.update(&enc[16..]).unwrap();
+            assert_eq!(dec.finalize(&[]), Err(CryptoError::AuthenticationFailed));
+        }
     }
 }
@@ -1,3 +1,5 @@
+use crate::crypto::symmetric_algs::BlockCipher;
+
 pub const SHIFTS: [u8; 16] = [1, 1, 2, 2, 2, 2, 2, 2, 1, 2, 2, 2, 2, 2, 2, 1];
 
 pub const SBOXES: [[u8; 64]; 8] = [
@@ -96,24 +98,7 @@ fn ip(mut message: u64) -> u64 {
     delta_swap(message, 24, 0x000000FF000000FF)
 }
 
-fn e(block: u64) -> u64 {
-    const BLOCK_LEN: usize = 32;
-    const RESULT_LEN: usize = 48;
-
-    let b1 = (block << (BLOCK_LEN - 1)) & 0x8000000000000000;
-    let b2 = (block >> 1) & 0x7C00000000000000;
-    let b3 = (block >> 3) & 0x03F0000000000000;
-    let b4 = (block >> 5) & 0x000FC00000000000;
-    let b5 = (block >> 7) & 0x00003F0000000000;
-    let b6 = (block >> 9) & 0x000000FC00000000;
-    let b7 = (block >> 11) & 0x00000003F0000000;
-    let b8 = (block >> 13) & 0x000000000FC00000;
-    let b9 = (block >> 15) & 0x00000000003E0000;
-    let b10 = (block >> (RESULT_LEN - 1)) & 0x0000000000010000;
-    b1 | b2 | b3 | b4 | b5 | b6 | b7 | b8 | b9 | b10
-}
-
-fn p(block: u64) -> u64 {
+const fn p(block: u64) -> u64 {
     let block = block.rotate_left(44);
     let b1 = (block & 0x0000000000200000) << 32;
     let b2 = (block & 0x0000000000480000) << 13;
@@ -129,37 +114,65 @@ fn p(block: u64) -> u64 {
     b1 | b2 | b3 | b4 | b5 | b6 | b7 | b8 | b9 | b10
 }
 
+// S-box lookups with the P permutation already applied, one table per S-box.
+// The output is the 32-bit half block.
+const SP: [[u32; 64]; 8] = {
+    let mut sp = [[0; 64]; 8];
+    let mut i = 0;
+    while i < 8 {
+        let mut x = 0;
+        while x < 64 {
+            sp[i][x] = (p((SBOXES[i][x] as u64) << (60 - 4 * i)) >> 32) as u32;
+            x += 1;
+        }
+        i += 1;
+    }
+    sp
+};
+
+// Round function on a 32-bit half. Expansion picks the six bits around every
+// nibble, so it is a rotation per S-box; the round key sits in the top 48 bits.
+fn f(r: u32, key: u64) -> u32 {
+    let mut out = 0;
+    for (i, sp) in SP.iter().enumerate() {
+        let e = r.rotate_left(4 * i as u32).rotate_right(1) >> 26;
+        let k = (key >> (58 - 6 * i)) as u32 & 0x3f;
+        out |= sp[(e ^ k) as usize];
+    }
+    out
+}
+
 fn rotate(mut val: u64, shift: u8) -> u64 {
     let top_bits = val >> (28 - shift);
     val <<= shift;
     (val | top_bits) & 0x0FFFFFFF
 }
 
-fn round(input: u64, key: u64) -> u64 {
-    let l = input & (0xFFFF_FFFF << 32);
-    let r = input << 32;
-
-    r | ((f(r, key) ^ l) >> 32)
-}
-
-fn f(input: u64, key: u64) -> u64 {
-    let mut val = e(input as u64);
-    val ^= key;
-    val = apply_sboxes(val);
-    p(val)
-}
-
-fn apply_sboxes(input: u64) -> u64 {
-    let mut output: u64 = 0;
-
-    for (i, sbox) in SBOXES.iter().enumerate() {
-        let val = (input >> (58 - (i * 6))) & 0x3F;
-        output |= u64::from(sbox[val as usize]) << (60 - (i * 4));
-    }
+// FIPS 74, with odd parity
+pub const WEAK_KEYS: [u64; 4] = [
+    0x0101_0101_0101_0101,
+    0xfefe_fefe_fefe_fefe,
+    0xe0e0_e0e0_f1f1_f1f1,
+    0x1f1f_1f1f_0e0e_0e0e,
+];
 
-    output
-}
+// in pairs, one key decrypts what the other encrypts
+pub const SEMI_WEAK_KEYS: [u64; 12] = [
+    0x011f_011f_010e_010e,
+    0x1f01_1f01_0e01_0e01,
+    0x01e0_01e0_01f1_01f1,
+    0xe001_e001_f101_f101,
+    0x01fe_01fe_01fe_01fe,
+    0xfe01_fe01_fe01_fe01,
+    0x1fe0_1fe0_0ef1_0ef1,
+    0xe01f_e01f_f10e_f10e,
+    0x1ffe_1ffe_0efe_0efe,
+    0xfe1f_fe1f_fe0e_fe0e,
+    0xe0fe_e0fe_f1fe_f1fe,
+    0xfee0_fee0_fef1_fef1,
+];
 
+#[derive(Copy, Clone, Debug, Default)]
 pub struct DES {
     pub keys: [u64; 16],
 }
@@ -178,26 +191,95 @@ fn gen_keys(key: u64) -> [u64; 16] {
 }
 
 impl DES {
+    // sets the lowest bit of every byte so each byte has odd parity
+    pub fn fix_parity(key: u64) -> u64 {
+        let bytes = key.to_be_bytes().map(|b| {
+            let b = b & 0xfe;
+            b | (b.count_ones() as u8 + 1) & 1
+        });
+        u64::from_be_bytes(bytes)
+    }
+
+    pub fn check_parity(key: u64) -> bool {
+        key.to_be_bytes().iter().all(|b| b.count_ones() % 2 == 1)
+    }
+
+    // parity bits are not part of the key, so they are ignored here
+    pub fn is_weak_key(key: u64) -> bool {
+        WEAK_KEYS.contains(&Self::fix_parity(key))
+    }
+
+    pub fn is_semi_weak_key(key: u64) -> bool {
+        SEMI_WEAK_KEYS.contains(&Self::fix_parity(key))
+    }
+
     pub fn new(key: u64) -> Self {
         Self {
-            keys: gen_keys(pc1(key)),
+            keys: gen_keys(key),
         }
     }
 
-    pub fn encrypt(&self, mut data: u64) -> u64 {
-        data = ip(data);
-        for key in &self.keys {
-            data = round(data, *key);
+    // The schedule before PC-1 stopped being applied twice, kept to read
+    // data encrypted with it
+    pub fn with_legacy_schedule(key: u64) -> Self {
+        Self {
+            keys: gen_keys(pc1(key)),
         }
-        fp((data << 32) | (data >> 32))
     }
 
-    pub fn decrypt(&self, mut data: u64) -> u64 {
-        data = ip(data);
-        for key in self.keys.iter().rev() {
-            data = round(data, *key);
+    fn crypt<'a>(data: u64, keys: impl Iterator<Item = &'a u64>) -> u64 {
+        let data = ip(data);
+        let (mut l, mut r) = ((data >> 32) as u32, data as u32);
+        for key in keys {
+            (l, r) = (r, l ^ f(r, *key));
         }
-        fp((data << 32) | (data >> 32))
+        fp(((r as u64) << 32) | l as u64)
+    }
+
+    pub fn encrypt(&self, data: u64) -> u64 {
+        Self::crypt(data, self.keys.iter())
+    }
+
+    pub fn decrypt(&self, data: u64) -> u64 {
+        Self::crypt(data, self.keys.iter().rev())
+    }
+}
+
+impl BlockCipher for DES {
+    type Block = u64;
+    type Key = u64;
+
+    fn with_key(key: u64) -> Self {
+        Self::new(key)
+    }
+
+    fn encrypt(&self, input: u64) -> u64 {
+        DES::encrypt(self, input)
+    }
+
+    fn decrypt(&self, input: u64) -> u64 {
+        DES::decrypt(self, input)
+    }
+}
+
+// DES with the legacy key schedule, not standard DES
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LegacyDES(DES);
+
+impl BlockCipher for LegacyDES {
+    type Block = u64;
+    type Key = u64;
+
+    fn with_key(key: u64) -> Self {
+        Self(DES::with_legacy_schedule(key))
+    }
+
+    fn encrypt(&self, input: u64) -> u64 {
+        self.0.encrypt(input)
+    }
+
+    fn decrypt(&self, input: u64) -> u64 {
+        self.0.decrypt(input)
     }
 }
 
@@ -206,6 +288,70 @@ mod tests {
     use super::*;
     use rand::random;
 
+    // the bitwise implementation the tables were derived from
+    mod reference {
+        use super::super::*;
+
+        fn e(block: u64) -> u64 {
+            const BLOCK_LEN: usize = 32;
+            const RESULT_LEN: usize = 48;
+
+            let b1 = (block << (BLOCK_LEN - 1)) & 0x8000000000000000;
+            let b2 = (block >> 1) & 0x7C00000000000000;
+            let b3 = (block >> 3) & 0x03F0000000000000;
+            let b4 = (block >> 5) & 0x000FC00000000000;
+            let b5 = (block >> 7) & 0x00003F0000000000;
+            let b6 = (block >> 9) & 0x000000FC00000000;
+            let b7 = (block >> 11) & 0x00000003F0000000;
+            let b8 = (block >> 13) & 0x000000000FC00000;
+            let b9 = (block >> 15) & 0x00000000003E0000;
+            let b10 = (block >> (RESULT_LEN - 1)) & 0x0000000000010000;
+            b1 | b2 | b3 | b4 | b5 | b6 | b7 | b8 | b9 | b10
+        }
+
+        fn round(input: u64, key: u64) -> u64 {
+            let l = input & (0xFFFF_FFFF << 32);
+            let r = input << 32;
+
+            r | ((f(r, key) ^ l) >> 32)
+        }
+
+        fn f(input: u64, key: u64) -> u64 {
+            let mut val = e(input);
+            val ^= key;
+            val = apply_sboxes(val);
+            p(val)
+        }
+
+        fn apply_sboxes(input: u64) -> u64 {
+            let mut output: u64 = 0;
+
+            for (i, sbox) in SBOXES.iter().enumerate() {
+                let val = (input >> (58 - (i * 6))) & 0x3F;
+                output |= u64::from(sbox[val as usize]) << (60 - (i * 4));
+            }
+
+            output
+        }
+
+        pub fn encrypt(des: &DES, mut data: u64) -> u64 {
+            data = ip(data);
+            for key in &des.keys {
+                data = round(data, *key);
+            }
+            fp(data.rotate_left(32))
+        }
+    }
+
+    #[test]
+    fn test_tables() {
+        for _ in 0..1000 {
+            let des = DES::new(random());
+            let data = random();
+            assert_eq!(des.encrypt(data), reference::encrypt(&des, data));
+        }
+    }
+
     #[test]
     fn test_des() {
         let key = random();
@@ -221,5 +367,47 @@ mod tests {
         };
 
         assert_eq!(data, de);
+
+        // FIPS 46-3 worked example
+        let des = DES::new(0x1334_5779_9bbc_dff1);
+        assert_eq!(des.encrypt(0x0123_4567_89ab_cdef), 0x85e8_1354_0f0a_b405);
+    }
+
+    #[test]
+    fn test_weak_keys() {
+        let data = random();
+        for key in WEAK_KEYS {
+            // encryption is an involution under a weak key
+            let des = DES::new(key);
+            assert_eq!(des.encrypt(des.encrypt(data)), data);
+            assert!(DES::is_weak_key(key ^ 0x0101_0101_0101_0101));
+        }
+        for pair in SEMI_WEAK_KEYS.chunks(2) {
+            let (a, b) = (DES::new(pair[0]), DES::new(pair[1]));
+            assert_eq!(b.encrypt(a.encrypt(data)), data);
+            assert!(DES::is_semi_weak_key(pair[1]));
+        }
+        assert!(!DES::is_weak_key(0x1334_5779_9bbc_dff1));
+        assert!(!DES::is_semi_weak_key(0x1334_5779_9bbc_dff1));
+    }
+
+    #[test]
+    fn test_parity() {
+        assert!(DES::check_parity(0x1334_5779_9bbc_dff1));
+        assert!(!DES::check_parity(0x1234_5678_9abc_def0));
+        assert_eq!(
+            DES::fix_parity(0x0000_0000_0000_0000),
+            0x0101_0101_0101_0101
+        );
+        assert_eq!(
+            DES::fix_parity(0x1234_5678_9abc_def0),
+            0x1334_5779_9bbc_dff1
+        );
+        let key = random();
+        assert!(DES::check_parity(DES::fix_parity(key)));
+        assert_eq!(
+            DES::fix_parity(key) & !0x0101_0101_0101_0101,
+            key & !0x0101_0101_0101_0101
+        );
     }
 }
@@ -0,0 +1,51 @@
+use crate::crypto::symmetric_algs::{BlockCipher, DES};
+
+// DES-X: DES with key whitening before and after, c = k2 ^ DES_k(p ^ k1).
+// The key is k | k1 | k2.
+pub struct DESX {
+    des: DES,
+    pre: u64,
+    post: u64,
+}
+
+impl BlockCipher for DESX {
+    type Block = u64;
+    type Key = [u64; 3];
+
+    fn with_key(key: [u64; 3]) -> Self {
+        Self {
+            des: DES::new(key[0]),
+            pre: key[1],
+            post: key[2],
+        }
+    }
+
+    fn encrypt(&self, input: u64) -> u64 {
+        self.des.encrypt(input ^ self.pre) ^ self.post
+    }
+
+    fn decrypt(&self, input: u64) -> u64 {
+        self.des.decrypt(input ^ self.post) ^ self.pre
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use rand::random;
+
+    #[test]
+    fn test_desx() {
+        let key = 0x133457799bbcdff1;
+        let desx = DESX::with_key([key, 0, 0]);
+        assert_eq!(desx.encrypt(0x0123456789abcdef), 0x85e813540f0ab405);
+
+        let key = DESX::generate_key(&mut OsRng);
+        let desx = DESX::with_key(key);
+        let data = random();
+        let expected = DES::new(key[0]).encrypt(data ^ key[1]) ^ key[2];
+        assert_eq!(desx.encrypt(data), expected);
+        assert_eq!(desx.decrypt(expected), data);
+    }
+}
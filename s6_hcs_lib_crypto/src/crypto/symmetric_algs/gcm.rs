@@ -0,0 +1,404 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::mac::ct_eq;
+use crate::crypto::progress::{CancelToken, Progress, Tracker};
+use crate::crypto::rng::SecureRng;
+use crate::crypto::symmetric_algs::{BlockCipher, StreamDecryptor, StreamEncryptor};
+use rayon::prelude::*;
+
+// bytes between progress reports and cancellation checks
+const STEP_SIZE: usize = 1 << 20;
+
+// SP 800-38D limits the plaintext to 2^39 - 256 bits, the 32-bit counter
+// would wrap around past that
+const MAX_LEN: u64 = (1 << 36) - 32;
+
+fn check_len(len: u64) -> Result<(), CryptoError> {
+    match len <= MAX_LEN {
+        true => Ok(()),
+        false => Err(CryptoError::LimitExceeded),
+    }
+}
+
+pub struct GCM<C: BlockCipher<Block = u128>> {
+    cipher: C,
+    h: u128,
+}
+
+// Both operands are secret (H and the data), so the bits select through
+// masks rather than branches
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        let bit = (x >> (127 - i)) & 1;
+        z ^= 0u128.wrapping_sub(bit) & v;
+        let carry = v & 1;
+        v = (v >> 1) ^ (0u128.wrapping_sub(carry) & R);
+    }
+    z
+}
+
+fn to_block(chunk: &[u8]) -> u128 {
+    let mut block = [0u8; 16];
+    block[..chunk.len()].copy_from_slice(chunk);
+    u128::from_be_bytes(block)
+}
+
+struct GHash {
+    h: u128,
+    y: u128,
+    aad_len: usize,
+    len: usize,
+}
+
+impl GHash {
+    fn new(h: u128, aad: &[u8]) -> Self {
+        let mut ghash = Self {
+            h,
+            y: 0,
+            aad_len: aad.len(),
+            len: 0,
+        };
+        ghash.absorb(aad);
+        ghash
+    }
+
+    // every call but the last has to be a multiple of 16 bytes
+    fn absorb(&mut self, data: &[u8]) {
+        for chunk in data.chunks(16) {
+            self.y = gf_mul(self.y ^ to_block(chunk), self.h);
+        }
+    }
+
+    fn update(&mut self, ciphertext: &[u8]) {
+        self.absorb(ciphertext);
+        self.len += ciphertext.len();
+    }
+
+    fn finalize(&self) -> u128 {
+        let lengths = ((self.aad_len as u128 * 8) << 64) | (self.len as u128 * 8);
+        gf_mul(self.y ^ lengths, self.h)
+    }
+}
+
+fn inc32(block: u128, n: u32) -> u128 {
+    let counter = (block as u32).wrapping_add(n);
+    (block & !0xffff_ffff) | counter as u128
+}
+
+impl<C: BlockCipher<Block = u128>> GCM<C> {
+    pub const IV_SIZE: usize = 12;
+    pub const TAG_SIZE: usize = 16;
+
+    pub fn generate_iv(rng: &mut dyn SecureRng) -> Vec<u8> {
+        let mut iv = vec![0; Self::IV_SIZE];
+        rng.fill_bytes(&mut iv);
+        iv
+    }
+
+    pub fn with_key(key: C::Key) -> Self {
+        let cipher = C::with_key(key);
+        let h = cipher.encrypt(0);
+        Self { cipher, h }
+    }
+
+    fn ghash(&self, aad: &[u8], input: &[u8]) -> u128 {
+        let mut ghash = GHash::new(self.h, aad);
+        ghash.update(input);
+        ghash.finalize()
+    }
+
+    fn pre_counter(&self, iv: &[u8]) -> u128 {
+        if iv.len() == Self::IV_SIZE {
+            (to_block(iv) & !0xffff_ffff) | 1
+        } else {
+            self.ghash(&[], iv)
+        }
+    }
+
+    fn apply_keystream(&self, j0: u128, start: u32, data: &mut [u8]) {
+        data.par_chunks_mut(16).enumerate().for_each(|(i, chunk)| {
+            let ks = self
+                .cipher
+                .encrypt(inc32(j0, start + i as u32 + 1))
+                .to_be_bytes();
+            chunk.iter_mut().zip(ks).for_each(|(b, k)| *b ^= k);
+        });
+    }
+
+    // applies the keystream in steps, reporting progress in between
+    fn apply_tracked(
+        &self,
+        j0: u128,
+        data: &mut [u8],
+        tracker: &Tracker,
+    ) -> Result<(), CryptoError> {
+        for (i, step) in data.chunks_mut(STEP_SIZE).enumerate() {
+            tracker.check()?;
+            self.apply_keystream(j0, (i * STEP_SIZE / 16) as u32, step);
+            tracker.advance(step.len() as u64)?;
+        }
+        Ok(())
+    }
+
+    fn tag(&self, j0: u128, aad: &[u8], ciphertext: &[u8]) -> u128 {
+        self.cipher.encrypt(j0) ^ self.ghash(aad, ciphertext)
+    }
+
+    pub fn encrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        input: &[u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(Vec<u8>, u128), CryptoError> {
+        check_len(input.len() as u64)?;
+        let j0 = self.pre_counter(iv);
+        let mut output = input.to_vec();
+        let tracker = Tracker::new(progress, cancel, input.len() as u64);
+        self.apply_tracked(j0, &mut output, &tracker)?;
+        let tag = self.tag(j0, aad, &output);
+        Ok((output, tag))
+    }
+
+    pub fn decrypt(
+        &self,
+        iv: &[u8],
+        aad: &[u8],
+        input: &[u8],
+        tag: u128,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<u8>, CryptoError> {
+        check_len(input.len() as u64)?;
+        let j0 = self.pre_counter(iv);
+        if !ct_eq(&self.tag(j0, aad, input).to_be_bytes(), &tag.to_be_bytes()) {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        let mut output = input.to_vec();
+        let tracker = Tracker::new(progress, cancel, input.len() as u64);
+        self.apply_tracked(j0, &mut output, &tracker)?;
+        Ok(output)
+    }
+
+    pub fn encryptor(self, iv: &[u8], aad: &[u8]) -> GCMEncryptor<C> {
+        GCMEncryptor(GCMStream::new(self, iv, aad))
+    }
+
+    pub fn decryptor(self, iv: &[u8], aad: &[u8]) -> GCMDecryptor<C> {
+        GCMDecryptor(GCMStream::new(self, iv, aad))
+    }
+}
+
+struct GCMStream<C: BlockCipher<Block = u128>> {
+    gcm: GCM<C>,
+    iv: Vec<u8>,
+    j0: u128,
+    ghash: GHash,
+    blocks: u32,
+    buffer: Vec<u8>,
+}
+
+impl<C: BlockCipher<Block = u128>> GCMStream<C> {
+    fn new(gcm: GCM<C>, iv: &[u8], aad: &[u8]) -> Self {
+        Self {
+            j0: gcm.pre_counter(iv),
+            ghash: GHash::new(gcm.h, aad),
+            iv: iv.to_vec(),
+            gcm,
+            blocks: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn take(&mut self, input: &[u8], all: bool) -> Vec<u8> {
+        self.buffer.extend_from_slice(input);
+        let n = if all {
+            self.buffer.len()
+        } else {
+            self.buffer.len() / 16 * 16
+        };
+        self.buffer.drain(..n).collect()
+    }
+
+    // only the last call may end in a partial block
+    fn apply(&mut self, data: &mut [u8]) -> Result<(), CryptoError> {
+        check_len(self.blocks as u64 * 16 + data.len() as u64)?;
+        self.gcm.apply_keystream(self.j0, self.blocks, data);
+        self.blocks += data.len().div_ceil(16) as u32;
+        Ok(())
+    }
+
+    fn tag(&self) -> u128 {
+        self.gcm.cipher.encrypt(self.j0) ^ self.ghash.finalize()
+    }
+}
+
+pub struct GCMEncryptor<C: BlockCipher<Block = u128>>(GCMStream<C>);
+
+pub struct GCMDecryptor<C: BlockCipher<Block = u128>>(GCMStream<C>);
+
+impl<C: BlockCipher<Block = u128>> StreamEncryptor for GCMEncryptor<C> {
+    fn iv(&self) -> Vec<u8> {
+        self.0.iv.clone()
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(input, false);
+        self.0.apply(&mut data)?;
+        self.0.ghash.update(&data);
+        Ok(data)
+    }
+
+    fn finalize(&mut self) -> Result<(Vec<u8>, Vec<u8>), CryptoError> {
+        let mut data = self.0.take(&[], true);
+        self.0.apply(&mut data)?;
+        self.0.ghash.update(&data);
+        Ok((data, self.0.tag().to_be_bytes().to_vec()))
+    }
+}
+
+// Plaintext is released before the tag is verified, see decrypt_stream
+impl<C: BlockCipher<Block = u128>> StreamDecryptor for GCMDecryptor<C> {
+    fn tag_size(&self) -> usize {
+        GCM::<C>::TAG_SIZE
+    }
+
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(input, false);
+        self.0.ghash.update(&data);
+        self.0.apply(&mut data)?;
+        Ok(data)
+    }
+
+    fn finalize(&mut self, tag: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let mut data = self.0.take(&[], true);
+        self.0.ghash.update(&data);
+        self.0.apply(&mut data)?;
+        if !ct_eq(&self.0.tag().to_be_bytes(), tag) {
+            return Err(CryptoError::AuthenticationFailed);
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::symmetric_algs::{AES128, DEAL128};
+    use crate::crypto::test_util::hex;
+    use rand::random;
+
+    #[test]
+    fn test_gcm_aes() {
+        // "The Galois/Counter Mode of Operation (GCM)", test cases 2 and 4
+        let gcm = GCM::<AES128>::with_key(0);
+        let (ct, tag) = gcm
+            .encrypt(&[0; 12], &[], &[0; 16], &(), &CancelToken::new())
+            .unwrap();
+        assert_eq!(ct, hex("0388dace60b6a392f328c2b971b2fe78"));
+        assert_eq!(tag, 0xab6e47d42cec13bdf53a67b21257bddf);
+
+        let gcm = GCM::<AES128>::with_key(0xfeffe9928665731c6d6a8f9467308308);
+        let iv = hex("cafebabefacedbaddecaf888");
+        let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+        let pt = hex(concat!(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72",
+            "1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39"
+        ));
+        let (ct, tag) = gcm
+            .encrypt(&iv, &aad, &pt, &(), &CancelToken::new())
+            .unwrap();
+        assert_eq!(
+            ct,
+            hex(concat!(
+                "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e",
+                "21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091"
+            ))
+        );
+        assert_eq!(tag, 0x5bc94fbc3221a5db94fae95ae7121a47);
+        assert_eq!(
+            gcm.decrypt(&iv, &aad, &ct, tag, &(), &CancelToken::new()),
+            Ok(pt)
+        );
+    }
+
+    #[test]
+    fn test_gcm_deal() {
+        let gcm = GCM::<DEAL128>::with_key(DEAL128::generate_key(&mut OsRng));
+        let iv = GCM::<DEAL128>::generate_iv(&mut OsRng);
+        let data: Vec<u8> = (0..1000).map(|_| random()).collect();
+        let (mut ct, tag) = gcm
+            .encrypt(&iv, b"file.txt", &data, &(), &CancelToken::new())
+            .unwrap();
+        assert_eq!(
+            gcm.decrypt(&iv, b"file.txt", &ct, tag, &(), &CancelToken::new()),
+            Ok(data)
+        );
+        assert_eq!(
+            gcm.decrypt(&iv, b"other.txt", &ct, tag, &(), &CancelToken::new()),
+            Err(CryptoError::AuthenticationFailed)
+        );
+        ct[17] ^= 1;
+        assert_eq!(
+            gcm.decrypt(&iv, b"file.txt", &ct, tag, &(), &CancelToken::new()),
+            Err(CryptoError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_gcm_stream() {
+        let key = DEAL128::generate_key(&mut OsRng);
+        let iv = GCM::<DEAL128>::generate_iv(&mut OsRng);
+        let data: Vec<u8> = (0..1000).map(|_| random()).collect();
+        let (ct, tag) = GCM::<DEAL128>::with_key(key)
+            .encrypt(&iv, b"aad", &data, &(), &CancelToken::new())
+            .unwrap();
+
+        let mut enc = GCM::<DEAL128>::with_key(key).encryptor(&iv, b"aad");
+        let mut out = Vec::new();
+        for chunk in data.chunks(37) {
+            out.extend(enc.update(chunk).unwrap());
+        }
+        let (tail, stream_tag) = enc.finalize().unwrap();
+        out.extend(tail);
+        assert_eq!(out, ct);
+        assert_eq!(stream_tag, tag.to_be_bytes());
+
+        let mut dec = GCM::<DEAL128>::with_key(key).decryptor(&iv, b"aad");
+        let mut out = Vec::new();
+        for chunk in ct.chunks(53) {
+            out.extend(dec.update(chunk).unwrap());
+        }
+        out.extend(dec.finalize(&stream_tag).unwrap());
+        assert_eq!(out, data);
+
+        let mut dec = GCM::<DEAL128>::with_key(key).decryptor(&iv, b"aad");
+        dec.update(&ct).unwrap();
+        assert_eq!(
+            dec.finalize(&[0; 16]),
+            Err(CryptoError::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn test_gcm_limit() {
+        assert_eq!(check_len(MAX_LEN), Ok(()));
+        assert_eq!(check_len(MAX_LEN + 1), Err(CryptoError::LimitExceeded));
+
+        // a stream one block short of the limit takes that block and no more
+        let key = DEAL128::generate_key(&mut OsRng);
+        let iv = GCM::<DEAL128>::generate_iv(&mut OsRng);
+        let mut enc = GCM::<DEAL128>::with_key(key).encryptor(&iv, &[]);
+        enc.0.blocks = (MAX_LEN / 16) as u32 - 1;
+        assert!(enc.update(&[0; 16]).is_ok());
+        assert_eq!(enc.update(&[0; 16]), Err(CryptoError::LimitExceeded));
+
+        let mut dec = GCM::<DEAL128>::with_key(key).decryptor(&iv, &[]);
+        dec.0.blocks = (MAX_LEN / 16) as u32;
+        assert_eq!(dec.update(&[0; 32]), Err(CryptoError::LimitExceeded));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::crypto::symmetric_algs::{Block, BlockCipher};
+use std::marker::PhantomData;
+
+const ROUNDS: usize = 20;
+const P: u32 = 0xb7e1_5163;
+const Q: u32 = 0x9e37_79b9;
+
+// RC6-32/20/b
+pub struct RC6<K: Block> {
+    round_keys: [u32; 2 * ROUNDS + 4],
+    _key: PhantomData<K>,
+}
+
+pub type RC6128 = RC6<u128>;
+pub type RC6192 = RC6<[u64; 3]>;
+pub type RC6256 = RC6<[u64; 4]>;
+
+fn to_words(bytes: &[u8]) -> [u32; 4] {
+    std::array::from_fn(|i| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap()))
+}
+
+fn from_words(words: [u32; 4]) -> u128 {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    u128::from_bytes(&bytes)
+}
+
+// x * (2x + 1) rotated by lg w
+fn mix(x: u32) -> u32 {
+    x.wrapping_mul(x.wrapping_mul(2).wrapping_add(1))
+        .rotate_left(5)
+}
+
+impl<K: Block> BlockCipher for RC6<K> {
+    type Block = u128;
+    type Key = K;
+
+    fn with_key(key: K) -> Self {
+        assert!([16, 24, 32].contains(&K::BYTES));
+        let mut l: Vec<u32> = key
+            .to_bytes()
+            .chunks(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        let mut s = [0u32; 2 * ROUNDS + 4];
+        s[0] = P;
+        for i in 1..s.len() {
+            s[i] = s[i - 1].wrapping_add(Q);
+        }
+
+        let (mut a, mut b) = (0u32, 0u32);
+        let (mut i, mut j) = (0, 0);
+        for _ in 0..3 * s.len().max(l.len()) {
+            a = s[i].wrapping_add(a).wrapping_add(b).rotate_left(3);
+            s[i] = a;
+            b = l[j]
+                .wrapping_add(a)
+                .wrapping_add(b)
+                .rotate_left(a.wrapping_add(b));
+            l[j] = b;
+            i = (i + 1) % s.len();
+            j = (j + 1) % l.len();
+        }
+        Self {
+            round_keys: s,
+            _key: PhantomData,
+        }
+    }
+
+    fn encrypt(&self, input: u128) -> u128 {
+        let s = &self.round_keys;
+        let [mut a, mut b, mut c, mut d] = to_words(&input.to_bytes());
+        b = b.wrapping_add(s[0]);
+        d = d.wrapping_add(s[1]);
+        for i in 1..=ROUNDS {
+            let (t, u) = (mix(b), mix(d));
+            a = (a ^ t).rotate_left(u).wrapping_add(s[2 * i]);
+            c = (c ^ u).rotate_left(t).wrapping_add(s[2 * i + 1]);
+            (a, b, c, d) = (b, c, d, a);
+        }
+        a = a.wrapping_add(s[2 * ROUNDS + 2]);
+        c = c.wrapping_add(s[2 * ROUNDS + 3]);
+        from_words([a, b, c, d])
+    }
+
+    fn decrypt(&self, input: u128) -> u128 {
+        let s = &self.round_keys;
+        let [mut a, mut b, mut c, mut d] = to_words(&input.to_bytes());
+        c = c.wrapping_sub(s[2 * ROUNDS + 3]);
+        a = a.wrapping_sub(s[2 * ROUNDS + 2]);
+        for i in (1..=ROUNDS).rev() {
+            (a, b, c, d) = (d, a, b, c);
+            let (t, u) = (mix(b), mix(d));
+            c = c.wrapping_sub(s[2 * i + 1]).rotate_right(t) ^ u;
+            a = a.wrapping_sub(s[2 * i]).rotate_right(u) ^ t;
+        }
+        d = d.wrapping_sub(s[1]);
+        b = b.wrapping_sub(s[0]);
+        from_words([a, b, c, d])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::check_vector;
+
+    #[test]
+    fn test_rc6() {
+        // test vectors from the RC6 paper
+        let pt = "02132435465768798a9bacbdcedfe0f1";
+        check_vector::<RC6128>(
+            "00000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "8fc3a53656b1f778c129df4e9848a41e",
+        );
+        check_vector::<RC6128>(
+            "0123456789abcdef0112233445566778",
+            pt,
+            "524e192f4715c6231f51f6367ea43f18",
+        );
+        check_vector::<RC6192>(
+            "000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "6cd61bcb190b30384e8a3f168690ae82",
+        );
+        check_vector::<RC6192>(
+            "0123456789abcdef0112233445566778899aabbccddeeff0",
+            pt,
+            "688329d019e505041e52e92af95291d4",
+        );
+        check_vector::<RC6256>(
+            "0000000000000000000000000000000000000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "8f5fbd0510d15fa893fa3fda6e857ec2",
+        );
+        check_vector::<RC6256>(
+            "0123456789abcdef0112233445566778899aabbccddeeff01032547698badcfe",
+            pt,
+            "c8241816f0d7e48920ad16a1674e5d48",
+        );
+    }
+}
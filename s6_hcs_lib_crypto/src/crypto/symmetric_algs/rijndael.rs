@@ -0,0 +1,221 @@
+use crate::crypto::symmetric_algs::{Block, BlockCipher};
+use std::marker::PhantomData;
+
+const fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut p = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        a = xtime(a);
+        b >>= 1;
+    }
+    p
+}
+
+const fn xtime(a: u8) -> u8 {
+    (a << 1) ^ if a & 0x80 != 0 { 0x1b } else { 0 }
+}
+
+const fn gen_sbox() -> [u8; 256] {
+    let mut sbox = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        // a^254 is the multiplicative inverse in GF(2^8), 0 maps to 0
+        let mut inv = 1u8;
+        let mut e = 0;
+        while e < 254 {
+            inv = gf_mul(inv, i as u8);
+            e += 1;
+        }
+        let x = if i == 0 { 0 } else { inv };
+        sbox[i] =
+            x ^ x.rotate_left(1) ^ x.rotate_left(2) ^ x.rotate_left(3) ^ x.rotate_left(4) ^ 0x63;
+        i += 1;
+    }
+    sbox
+}
+
+const fn gen_inv_sbox(sbox: &[u8; 256]) -> [u8; 256] {
+    let mut inv = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        inv[sbox[i] as usize] = i as u8;
+        i += 1;
+    }
+    inv
+}
+
+pub const SBOX: [u8; 256] = gen_sbox();
+pub const INV_SBOX: [u8; 256] = gen_inv_sbox(&SBOX);
+
+pub struct Rijndael<B: Block, K: Block> {
+    nb: usize,
+    nr: usize,
+    round_keys: Vec<u8>,
+    _types: PhantomData<(B, K)>,
+}
+
+pub type AES128 = Rijndael<u128, u128>;
+pub type AES192 = Rijndael<u128, [u64; 3]>;
+pub type AES256 = Rijndael<u128, [u64; 4]>;
+
+fn shifts(nb: usize) -> [usize; 4] {
+    match nb {
+        8 => [0, 1, 3, 4],
+        7 => [0, 1, 2, 4],
+        _ => [0, 1, 2, 3],
+    }
+}
+
+fn expand_key(key: &[u8], nb: usize, nr: usize) -> Vec<u8> {
+    let nk = key.len() / 4;
+    let mut w = key.to_vec();
+    let mut rcon = 1u8;
+    for i in nk..nb * (nr + 1) {
+        let mut t: [u8; 4] = w[(i - 1) * 4..i * 4].try_into().unwrap();
+        if i % nk == 0 {
+            t.rotate_left(1);
+            t = t.map(|b| SBOX[b as usize]);
+            t[0] ^= rcon;
+            rcon = xtime(rcon);
+        } else if nk > 6 && i % nk == 4 {
+            t = t.map(|b| SBOX[b as usize]);
+        }
+        for j in 0..4 {
+            w.push(w[(i - nk) * 4 + j] ^ t[j]);
+        }
+    }
+    w
+}
+
+impl<B: Block, K: Block> Rijndael<B, K> {
+    fn add_round_key(&self, state: &mut [u8], round: usize) {
+        let len = self.nb * 4;
+        let rk = &self.round_keys[round * len..(round + 1) * len];
+        state.iter_mut().zip(rk).for_each(|(s, k)| *s ^= k);
+    }
+
+    fn shift_rows(&self, state: &mut [u8], inverse: bool) {
+        let nb = self.nb;
+        let shifts = shifts(nb);
+        for r in 1..4 {
+            let row: Vec<u8> = (0..nb).map(|c| state[c * 4 + r]).collect();
+            for c in 0..nb {
+                let src = if inverse {
+                    (c + nb - shifts[r]) % nb
+                } else {
+                    (c + shifts[r]) % nb
+                };
+                state[c * 4 + r] = row[src];
+            }
+        }
+    }
+
+    fn mix_columns(state: &mut [u8], inverse: bool) {
+        let m: [u8; 4] = if inverse {
+            [0x0e, 0x0b, 0x0d, 0x09]
+        } else {
+            [0x02, 0x03, 0x01, 0x01]
+        };
+        for col in state.chunks_mut(4) {
+            let a: [u8; 4] = col.try_into().unwrap();
+            for r in 0..4 {
+                col[r] = gf_mul(a[r], m[0])
+                    ^ gf_mul(a[(r + 1) % 4], m[1])
+                    ^ gf_mul(a[(r + 2) % 4], m[2])
+                    ^ gf_mul(a[(r + 3) % 4], m[3]);
+            }
+        }
+    }
+}
+
+impl<B: Block, K: Block> BlockCipher for Rijndael<B, K> {
+    type Block = B;
+    type Key = K;
+
+    fn with_key(key: K) -> Self {
+        assert!([16, 20, 24, 28, 32].contains(&B::BYTES));
+        assert!([16, 20, 24, 28, 32].contains(&K::BYTES));
+        let nb = B::BYTES / 4;
+        let nr = nb.max(K::BYTES / 4) + 6;
+        Self {
+            nb,
+            nr,
+            round_keys: expand_key(&key.to_bytes(), nb, nr),
+            _types: PhantomData,
+        }
+    }
+
+    fn encrypt(&self, input: B) -> B {
+        let mut state = input.to_bytes();
+        self.add_round_key(&mut state, 0);
+        for round in 1..=self.nr {
+            state.iter_mut().for_each(|b| *b = SBOX[*b as usize]);
+            self.shift_rows(&mut state, false);
+            if round != self.nr {
+                Self::mix_columns(&mut state, false);
+            }
+            self.add_round_key(&mut state, round);
+        }
+        B::from_bytes(&state)
+    }
+
+    fn decrypt(&self, input: B) -> B {
+        let mut state = input.to_bytes();
+        self.add_round_key(&mut state, self.nr);
+        for round in (0..self.nr).rev() {
+            self.shift_rows(&mut state, true);
+            state.iter_mut().for_each(|b| *b = INV_SBOX[*b as usize]);
+            self.add_round_key(&mut state, round);
+            if round != 0 {
+                Self::mix_columns(&mut state, true);
+            }
+        }
+        B::from_bytes(&state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::test_util::check_vector;
+
+    #[test]
+    fn test_aes() {
+        // FIPS-197, appendix C
+        check_vector::<AES128>(
+            "000102030405060708090a0b0c0d0e0f",
+            "00112233445566778899aabbccddeeff",
+            "69c4e0d86a7b0430d8cdb78070b4c55a",
+        );
+        check_vector::<AES192>(
+            "000102030405060708090a0b0c0d0e0f1011121314151617",
+            "00112233445566778899aabbccddeeff",
+            "dda97ca4864cdfe06eaf70a0ec0d7191",
+        );
+        check_vector::<AES256>(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "8ea2b7ca516745bfeafc49904b496089",
+        );
+        // FIPS-197, appendix B
+        check_vector::<AES128>(
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3243f6a8885a308d313198a2e0370734",
+            "3925841d02dc09fbdc118597196a0b32",
+        );
+    }
+
+    #[test]
+    fn test_rijndael_wide_blocks() {
+        let data = [0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210, 42];
+        let cr = Rijndael::<[u64; 3], [u64; 4]>::with_key(Block::random(&mut OsRng));
+        assert_eq!(cr.decrypt(cr.encrypt(data)), data);
+
+        let data = [1, 2, 3, 4];
+        let cr = Rijndael::<[u64; 4], u128>::with_key(Block::random(&mut OsRng));
+        assert_eq!(cr.decrypt(cr.encrypt(data)), data);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::crypto::symmetric_algs::{Block, BlockCipher};
+use std::marker::PhantomData;
+
+const PHI: u32 = 0x9e37_79b9;
+
+const SBOX: [[u8; 16]; 8] = [
+    [3, 8, 15, 1, 10, 6, 5, 11, 14, 13, 4, 2, 7, 0, 9, 12],
+    [15, 12, 2, 7, 9, 0, 5, 10, 1, 11, 14, 8, 6, 13, 3, 4],
+    [8, 6, 7, 9, 3, 12, 10, 15, 13, 1, 14, 4, 0, 11, 5, 2],
+    [0, 15, 11, 8, 12, 9, 6, 3, 13, 1, 2, 4, 10, 7, 5, 14],
+    [1, 15, 8, 3, 12, 0, 11, 6, 2, 5, 4, 10, 9, 14, 7, 13],
+    [15, 5, 2, 11, 4, 10, 9, 12, 0, 3, 14, 8, 13, 6, 7, 1],
+    [7, 2, 12, 5, 8, 4, 6, 11, 14, 9, 1, 15, 13, 3, 10, 0],
+    [1, 13, 15, 0, 14, 8, 2, 11, 7, 4, 12, 10, 9, 3, 5, 6],
+];
+
+const fn gen_inv_sboxes() -> [[u8; 16]; 8] {
+    let mut inv = [[0u8; 16]; 8];
+    let mut s = 0;
+    while s < 8 {
+        let mut i = 0;
+        while i < 16 {
+            inv[s][SBOX[s][i] as usize] = i as u8;
+            i += 1;
+        }
+        s += 1;
+    }
+    inv
+}
+
+const INV_SBOX: [[u8; 16]; 8] = gen_inv_sboxes();
+
+// Bitslice mode, word i holds bit i of every 4-bit S-box input
+fn substitute(x: [u32; 4], sbox: &[u8; 16]) -> [u32; 4] {
+    let mut out = [0u32; 4];
+    for bit in 0..32 {
+        let nibble = (0..4).fold(0, |n, i| n | ((x[i] >> bit) & 1) << i);
+        let s = sbox[nibble as usize] as u32;
+        for (i, o) in out.iter_mut().enumerate() {
+            *o |= ((s >> i) & 1) << bit;
+        }
+    }
+    out
+}
+
+fn transform(x: [u32; 4]) -> [u32; 4] {
+    let [mut x0, mut x1, mut x2, mut x3] = x;
+    x0 = x0.rotate_left(13);
+    x2 = x2.rotate_left(3);
+    x1 ^= x0 ^ x2;
+    x3 ^= x2 ^ (x0 << 3);
+    x1 = x1.rotate_left(1);
+    x3 = x3.rotate_left(7);
+    x0 ^= x1 ^ x3;
+    x2 ^= x3 ^ (x1 << 7);
+    x0 = x0.rotate_left(5);
+    x2 = x2.rotate_left(22);
+    [x0, x1, x2, x3]
+}
+
+fn inv_transform(x: [u32; 4]) -> [u32; 4] {
+    let [mut x0, mut x1, mut x2, mut x3] = x;
+    x2 = x2.rotate_right(22);
+    x0 = x0.rotate_right(5);
+    x2 ^= x3 ^ (x1 << 7);
+    x0 ^= x1 ^ x3;
+    x3 = x3.rotate_right(7);
+    x1 = x1.rotate_right(1);
+    x3 ^= x2 ^ (x0 << 3);
+    x1 ^= x0 ^ x2;
+    x2 = x2.rotate_right(3);
+    x0 = x0.rotate_right(13);
+    [x0, x1, x2, x3]
+}
+
+fn xor(a: [u32; 4], b: [u32; 4]) -> [u32; 4] {
+    std::array::from_fn(|i| a[i] ^ b[i])
+}
+
+// Words are little endian, as in the reference code and the NESSIE vectors
+fn to_words(bytes: &[u8]) -> [u32; 4] {
+    std::array::from_fn(|i| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap()))
+}
+
+fn from_words(words: [u32; 4]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+pub struct Serpent<K: Block> {
+    round_keys: [[u32; 4]; 33],
+    _key: PhantomData<K>,
+}
+
+pub type Serpent128 = Serpent<u128>;
+pub type Serpent192 = Serpent<[u64; 3]>;
+pub type Serpent256 = Serpent<[u64; 4]>;
+
+fn expand_key(key: &[u8]) -> [[u32; 4]; 33] {
+    // shorter keys are padded to 256 bits with a single 1 bit
+    let mut padded = key.to_vec();
+    padded.push(1);
+    padded.resize(32, 0);
+    let mut w: Vec<u32> = padded
+        .chunks(4)
+        .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    for i in 0..132 {
+        let t = w[i] ^ w[i + 3] ^ w[i + 5] ^ w[i + 7] ^ PHI ^ i as u32;
+        w.push(t.rotate_left(11));
+    }
+    std::array::from_fn(|i| {
+        let k = [w[8 + 4 * i], w[9 + 4 * i], w[10 + 4 * i], w[11 + 4 * i]];
+        substitute(k, &SBOX[(35 - i) % 8])
+    })
+}
+
+impl<K: Block> BlockCipher for Serpent<K> {
+    type Block = u128;
+    type Key = K;
+
+    fn with_key(key: K) -> Self {
+        assert!([16, 24, 32].contains(&K::BYTES));
+        Self {
+            round_keys: expand_key(&key.to_bytes()),
+            _key: PhantomData,
+        }
+    }
+
+    fn encrypt(&self, input: u128) -> u128 {
+        let mut x = to_words(&input.to_bytes());
+        for round in 0..32 {
+            x = substitute(xor(x, self.round_keys[round]), &SBOX[round % 8]);
+            x = match round {
+                31 => xor(x, self.round_keys[32]),
+                _ => transform(x),
+            };
+        }
+        u128::from_bytes(&from_words(x))
+    }
+
+    fn decrypt(&self, input: u128) -> u128 {
+        let mut x = to_words(&input.to_bytes());
+        for round in (0..32).rev() {
+            x = match round {
+                31 => xor(x, self.round_keys[32]),
+                _ => inv_transform(x),
+            };
+            x = xor(substitute(x, &INV_SBOX[round % 8]), self.round_keys[round]);
+        }
+        u128::from_bytes(&from_words(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::check_vector;
+
+    #[test]
+    fn test_serpent() {
+        // NESSIE vectors. The submission tables print every value byte
+        // reversed, these follow the byte order of the reference code.
+        check_vector::<Serpent128>(
+            "80000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "264e5481eff42a4606abda06c0bfda3d",
+        );
+        check_vector::<Serpent128>(
+            "00000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "3620b17ae6a993d09618b8768266bae9",
+        );
+        check_vector::<Serpent192>(
+            "000000000000000000000000000000000000000000000000",
+            "00112233445566778899aabbccddeeff",
+            "e532aebb2793497afc40efb832f4d14f",
+        );
+        check_vector::<Serpent256>(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "00112233445566778899aabbccddeeff",
+            "2868b7a2d28ecd5e4fdefac3c4330074",
+        );
+    }
+}
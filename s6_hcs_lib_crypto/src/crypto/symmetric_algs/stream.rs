@@ -0,0 +1,88 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::progress::{CancelToken, Progress, Tracker};
+use std::io::{self, Read, Write};
+
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+pub trait StreamEncryptor {
+    fn iv(&self) -> Vec<u8>;
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError>;
+    fn finalize(&mut self) -> Result<(Vec<u8>, Vec<u8>), CryptoError>;
+}
+
+pub trait StreamDecryptor {
+    fn tag_size(&self) -> usize;
+    fn update(&mut self, input: &[u8]) -> Result<Vec<u8>, CryptoError>;
+    fn finalize(&mut self, tag: &[u8]) -> Result<Vec<u8>, CryptoError>;
+}
+
+fn read_chunk(reader: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+// len is how much the reader will yield, it is only used to report progress
+pub fn encrypt_stream(
+    encryptor: &mut dyn StreamEncryptor,
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    len: u64,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> io::Result<()> {
+    let tracker = Tracker::new(progress, cancel, len);
+    writer.write_all(&encryptor.iv())?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let n = read_chunk(reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&encryptor.update(&buf[..n])?)?;
+        tracker.advance(n as u64)?;
+    }
+    let (tail, tag) = encryptor.finalize()?;
+    writer.write_all(&tail)?;
+    writer.write_all(&tag)?;
+    Ok(())
+}
+
+// The IV must already be consumed from the reader. Plaintext is written before
+// the tag is checked, so the caller has to discard the output on error.
+pub fn decrypt_stream(
+    decryptor: &mut dyn StreamDecryptor,
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    len: u64,
+    progress: &dyn Progress,
+    cancel: &CancelToken,
+) -> io::Result<()> {
+    let tracker = Tracker::new(progress, cancel, len);
+    let tag_size = decryptor.tag_size();
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut pending = Vec::new();
+    loop {
+        let n = read_chunk(reader, &mut buf)?;
+        if n == 0 {
+            break;
+        }
+        pending.extend_from_slice(&buf[..n]);
+        let split = pending.len().saturating_sub(tag_size);
+        writer.write_all(&decryptor.update(&pending[..split])?)?;
+        pending.drain(..split);
+        tracker.advance(n as u64)?;
+    }
+    let tail = decryptor
+        .finalize(&pending)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "corrupted ciphertext"))?;
+    writer.write_all(&tail)?;
+    Ok(())
+}
@@ -0,0 +1,271 @@
+use crate::crypto::symmetric_algs::{Block, BlockCipher};
+use std::marker::PhantomData;
+
+// 4-bit tables the two byte permutations are built from
+const Q_TABLES: [[[u8; 16]; 4]; 2] = [
+    [
+        [
+            0x8, 0x1, 0x7, 0xd, 0x6, 0xf, 0x3, 0x2, 0x0, 0xb, 0x5, 0x9, 0xe, 0xc, 0xa, 0x4,
+        ],
+        [
+            0xe, 0xc, 0xb, 0x8, 0x1, 0x2, 0x3, 0x5, 0xf, 0x4, 0xa, 0x6, 0x7, 0x0, 0x9, 0xd,
+        ],
+        [
+            0xb, 0xa, 0x5, 0xe, 0x6, 0xd, 0x9, 0x0, 0xc, 0x8, 0xf, 0x3, 0x2, 0x4, 0x7, 0x1,
+        ],
+        [
+            0xd, 0x7, 0xf, 0x4, 0x1, 0x2, 0x6, 0xe, 0x9, 0xb, 0x3, 0x0, 0x8, 0x5, 0xc, 0xa,
+        ],
+    ],
+    [
+        [
+            0x2, 0x8, 0xb, 0xd, 0xf, 0x7, 0x6, 0xe, 0x3, 0x1, 0x9, 0x4, 0x0, 0xa, 0xc, 0x5,
+        ],
+        [
+            0x1, 0xe, 0x2, 0xb, 0x4, 0xc, 0x3, 0x7, 0x6, 0xd, 0xa, 0x5, 0xf, 0x9, 0x0, 0x8,
+        ],
+        [
+            0x4, 0xc, 0x7, 0x5, 0x1, 0x6, 0x9, 0xa, 0x0, 0xe, 0xd, 0x8, 0x2, 0xb, 0x3, 0xf,
+        ],
+        [
+            0xb, 0x9, 0x5, 0x1, 0xc, 0x3, 0xd, 0xe, 0x6, 0x4, 0x7, 0xf, 0x2, 0x0, 0x8, 0xa,
+        ],
+    ],
+];
+
+const MDS: [[u8; 4]; 4] = [
+    [0x01, 0xef, 0x5b, 0x5b],
+    [0x5b, 0xef, 0xef, 0x01],
+    [0xef, 0x5b, 0x01, 0xef],
+    [0xef, 0x01, 0xef, 0x5b],
+];
+
+const RS: [[u8; 8]; 4] = [
+    [0x01, 0xa4, 0x55, 0x87, 0x5a, 0x58, 0xdb, 0x9e],
+    [0xa4, 0x56, 0x82, 0xf3, 0x1e, 0xc6, 0x68, 0xe5],
+    [0x02, 0xa1, 0xfc, 0xc1, 0x47, 0xae, 0x3d, 0x19],
+    [0xa4, 0x55, 0x87, 0x5a, 0x58, 0xdb, 0x9e, 0x03],
+];
+
+const fn ror4(x: u8) -> u8 {
+    ((x >> 1) | (x << 3)) & 0xf
+}
+
+const fn gen_q(t: &[[u8; 16]; 4]) -> [u8; 256] {
+    let mut q = [0u8; 256];
+    let mut x = 0;
+    while x < 256 {
+        let (a, b) = ((x >> 4) as u8, (x & 0xf) as u8);
+        let (a, b) = (a ^ b, a ^ ror4(b) ^ ((a << 3) & 0xf));
+        let (a, b) = (t[0][a as usize], t[1][b as usize]);
+        let (a, b) = (a ^ b, a ^ ror4(b) ^ ((a << 3) & 0xf));
+        let (a, b) = (t[2][a as usize], t[3][b as usize]);
+        q[x] = (b << 4) | a;
+        x += 1;
+    }
+    q
+}
+
+const Q0: [u8; 256] = gen_q(&Q_TABLES[0]);
+const Q1: [u8; 256] = gen_q(&Q_TABLES[1]);
+
+fn gf_mul(mut a: u8, mut b: u8, poly: u16) -> u8 {
+    let mut p = 0;
+    while b != 0 {
+        if b & 1 != 0 {
+            p ^= a;
+        }
+        a = ((a as u16) << 1 ^ if a & 0x80 != 0 { poly } else { 0 }) as u8;
+        b >>= 1;
+    }
+    p
+}
+
+// the S-boxes depend on the key, every byte goes through a chain of q
+// permutations with key bytes mixed in between, then through the MDS matrix
+fn h(x: u32, keys: &[u32]) -> u32 {
+    let mut y = x.to_le_bytes();
+    let xor_key = |y: &mut [u8; 4], k: u32| {
+        y.iter_mut().zip(k.to_le_bytes()).for_each(|(y, k)| *y ^= k);
+    };
+    if keys.len() == 4 {
+        y = [
+            Q1[y[0] as usize],
+            Q0[y[1] as usize],
+            Q0[y[2] as usize],
+            Q1[y[3] as usize],
+        ];
+        xor_key(&mut y, keys[3]);
+    }
+    if keys.len() >= 3 {
+        y = [
+            Q1[y[0] as usize],
+            Q1[y[1] as usize],
+            Q0[y[2] as usize],
+            Q0[y[3] as usize],
+        ];
+        xor_key(&mut y, keys[2]);
+    }
+    y = [
+        Q0[y[0] as usize],
+        Q1[y[1] as usize],
+        Q0[y[2] as usize],
+        Q1[y[3] as usize],
+    ];
+    xor_key(&mut y, keys[1]);
+    y = [
+        Q0[y[0] as usize],
+        Q0[y[1] as usize],
+        Q1[y[2] as usize],
+        Q1[y[3] as usize],
+    ];
+    xor_key(&mut y, keys[0]);
+    y = [
+        Q1[y[0] as usize],
+        Q0[y[1] as usize],
+        Q1[y[2] as usize],
+        Q0[y[3] as usize],
+    ];
+
+    let z: [u8; 4] =
+        std::array::from_fn(|i| (0..4).fold(0, |acc, j| acc ^ gf_mul(MDS[i][j], y[j], 0x169)));
+    u32::from_le_bytes(z)
+}
+
+pub struct Twofish<K: Block> {
+    round_keys: [u32; 40],
+    sbox_keys: Vec<u32>,
+    _key: PhantomData<K>,
+}
+
+pub type Twofish128 = Twofish<u128>;
+pub type Twofish192 = Twofish<[u64; 3]>;
+pub type Twofish256 = Twofish<[u64; 4]>;
+
+impl<K: Block> Twofish<K> {
+    fn g(&self, x: u32) -> u32 {
+        h(x, &self.sbox_keys)
+    }
+
+    fn f(&self, r0: u32, r1: u32, round: usize) -> (u32, u32) {
+        let t0 = self.g(r0);
+        let t1 = self.g(r1.rotate_left(8));
+        let f0 = t0
+            .wrapping_add(t1)
+            .wrapping_add(self.round_keys[2 * round + 8]);
+        let f1 = t0
+            .wrapping_add(t1.wrapping_mul(2))
+            .wrapping_add(self.round_keys[2 * round + 9]);
+        (f0, f1)
+    }
+}
+
+fn to_words(bytes: &[u8]) -> [u32; 4] {
+    std::array::from_fn(|i| u32::from_le_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap()))
+}
+
+fn from_words(words: [u32; 4]) -> u128 {
+    let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    u128::from_bytes(&bytes)
+}
+
+impl<K: Block> BlockCipher for Twofish<K> {
+    type Block = u128;
+    type Key = K;
+
+    fn with_key(key: K) -> Self {
+        assert!([16, 24, 32].contains(&K::BYTES));
+        let key = key.to_bytes();
+        let words: Vec<u32> = key
+            .chunks(4)
+            .map(|c| u32::from_le_bytes(c.try_into().unwrap()))
+            .collect();
+        let even: Vec<u32> = words.iter().step_by(2).copied().collect();
+        let odd: Vec<u32> = words.iter().skip(1).step_by(2).copied().collect();
+
+        // the last 64 bits of key give the first S-box key
+        let sbox_keys = key
+            .chunks(8)
+            .rev()
+            .map(|chunk| {
+                let s: [u8; 4] = std::array::from_fn(|i| {
+                    (0..8).fold(0, |acc, j| acc ^ gf_mul(RS[i][j], chunk[j], 0x14d))
+                });
+                u32::from_le_bytes(s)
+            })
+            .collect();
+
+        const RHO: u32 = 0x0101_0101;
+        let mut round_keys = [0; 40];
+        for i in 0..20 {
+            let a = h(2 * i as u32 * RHO, &even);
+            let b = h((2 * i as u32 + 1) * RHO, &odd).rotate_left(8);
+            round_keys[2 * i] = a.wrapping_add(b);
+            round_keys[2 * i + 1] = a.wrapping_add(b.wrapping_mul(2)).rotate_left(9);
+        }
+        Self {
+            round_keys,
+            sbox_keys,
+            _key: PhantomData,
+        }
+    }
+
+    fn encrypt(&self, input: u128) -> u128 {
+        let k = &self.round_keys;
+        let [mut r0, mut r1, mut r2, mut r3] = to_words(&input.to_bytes());
+        (r0, r1, r2, r3) = (r0 ^ k[0], r1 ^ k[1], r2 ^ k[2], r3 ^ k[3]);
+        for round in 0..16 {
+            let (f0, f1) = self.f(r0, r1, round);
+            let (n2, n3) = ((r2 ^ f0).rotate_right(1), r3.rotate_left(1) ^ f1);
+            (r0, r1, r2, r3) = (n2, n3, r0, r1);
+        }
+        // the last swap is undone
+        from_words([r2 ^ k[4], r3 ^ k[5], r0 ^ k[6], r1 ^ k[7]])
+    }
+
+    fn decrypt(&self, input: u128) -> u128 {
+        let k = &self.round_keys;
+        let [c0, c1, c2, c3] = to_words(&input.to_bytes());
+        let (mut r0, mut r1, mut r2, mut r3) = (c2 ^ k[6], c3 ^ k[7], c0 ^ k[4], c1 ^ k[5]);
+        for round in (0..16).rev() {
+            // the swap moved the round input halves r0, r1 to r2, r3
+            let (p0, p1) = (r2, r3);
+            let (f0, f1) = self.f(p0, p1, round);
+            let (p2, p3) = (r0.rotate_left(1) ^ f0, (r1 ^ f1).rotate_right(1));
+            (r0, r1, r2, r3) = (p0, p1, p2, p3);
+        }
+        from_words([r0 ^ k[0], r1 ^ k[1], r2 ^ k[2], r3 ^ k[3]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::test_util::check_vector;
+
+    #[test]
+    fn test_q_tables() {
+        assert_eq!(Q0[..4], [0xa9, 0x67, 0xb3, 0xe8]);
+        assert_eq!(Q1[..4], [0x75, 0xf3, 0xc6, 0xf4]);
+    }
+
+    #[test]
+    fn test_twofish() {
+        // test vectors from the Twofish paper
+        check_vector::<Twofish128>(
+            "00000000000000000000000000000000",
+            "00000000000000000000000000000000",
+            "9f589f5cf6122c32b6bfec2f2ae8c35a",
+        );
+        check_vector::<Twofish192>(
+            "0123456789abcdeffedcba98765432100011223344556677",
+            "00000000000000000000000000000000",
+            "cfd1d2e5a9be9cdf501f13b892bd2248",
+        );
+        check_vector::<Twofish256>(
+            "0123456789abcdeffedcba987654321000112233445566778899aabbccddeeff",
+            "00000000000000000000000000000000",
+            "37527be0052334b89f0cfccae87cfa20",
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::crypto::error::CryptoError;
+use crate::crypto::progress::{CancelToken, Progress, Tracker};
+use crate::crypto::symmetric_algs::BlockCipher;
+use rayon::prelude::*;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+
+// IEEE 1619 XTS. Every sector is encrypted on its own under a tweak derived
+// from its index, so any sector can be decrypted without the ones before it.
+// Ciphertext has the same length as the plaintext. Nothing is authenticated,
+// so it is a library API for data at rest and not one of the container modes.
+pub struct XTS<C: BlockCipher<Block = u128>> {
+    data: C,
+    tweak: C,
+}
+
+// multiplication by the primitive element, the tweak is little-endian
+fn mul_alpha(t: [u8; 16]) -> [u8; 16] {
+    let t = u128::from_le_bytes(t);
+    let carry = if t >> 127 != 0 { 0x87 } else { 0 };
+    ((t << 1) ^ carry).to_le_bytes()
+}
+
+fn xor(a: [u8; 16], b: [u8; 16]) -> [u8; 16] {
+    std::array::from_fn(|i| a[i] ^ b[i])
+}
+
+impl<C: BlockCipher<Block = u128>> XTS<C> {
+    pub const SECTOR_SIZE: usize = 4096;
+
+    // the two keys must differ, equal ones leak through the tweaks
+    pub fn with_keys(data_key: C::Key, tweak_key: C::Key) -> Result<Self, CryptoError> {
+        if data_key == tweak_key {
+            return Err(CryptoError::InvalidKey);
+        }
+        Ok(Self {
+            data: C::with_key(data_key),
+            tweak: C::with_key(tweak_key),
+        })
+    }
+
+    // A trailing piece shorter than a block cannot be stolen into, so it
+    // joins the sector before it
+    pub fn sector_count(len: u64) -> u64 {
+        let size = Self::SECTOR_SIZE as u64;
+        if len >= size && len % size < 16 {
+            len / size
+        } else {
+            len.div_ceil(size)
+        }
+    }
+
+    pub fn sector_range(len: u64, sector: u64) -> Range<u64> {
+        let size = Self::SECTOR_SIZE as u64;
+        let start = sector * size;
+        if sector + 1 == Self::sector_count(len) {
+            start..len
+        } else {
+            start..start + size
+        }
+    }
+
+    fn block(&self, input: [u8; 16], t: [u8; 16], encrypt: bool) -> [u8; 16] {
+        let x = u128::from_be_bytes(xor(input, t));
+        let y = if encrypt {
+            self.data.encrypt(x)
+        } else {
+            self.data.decrypt(x)
+        };
+        xor(y.to_be_bytes(), t)
+    }
+
+    fn process_sector(
+        &self,
+        sector: u64,
+        data: &mut [u8],
+        encrypt: bool,
+    ) -> Result<(), CryptoError> {
+        if data.len() < 16 {
+            return Err(CryptoError::InvalidLength);
+        }
+        let index = u128::from_be_bytes((sector as u128).to_le_bytes());
+        let mut t = self.tweak.encrypt(index).to_be_bytes();
+        let full = data.len() / 16 - if data.len().is_multiple_of(16) { 0 } else { 1 };
+        for chunk in data[..full * 16].chunks_mut(16) {
+            let out = self.block(chunk.try_into().unwrap(), t, encrypt);
+            chunk.copy_from_slice(&out);
+            t = mul_alpha(t);
+        }
+
+        let r = data.len() % 16;
+        if r != 0 {
+            // ciphertext stealing over the last full block and the partial one
+            let (t1, t2) = if encrypt {
+                (t, mul_alpha(t))
+            } else {
+                (mul_alpha(t), t)
+            };
+            let (head, tail) = data[full * 16..].split_at_mut(16);
+            let cc = self.block(head.try_into().unwrap(), t1, encrypt);
+            let mut pp = cc;
+            pp[..r].copy_from_slice(tail);
+            tail.copy_from_slice(&cc[..r]);
+            head.copy_from_slice(&self.block(pp, t2, encrypt));
+        }
+        Ok(())
+    }
+
+    // a sector needs at least one full block
+    pub fn encrypt_sector(&self, sector: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.process_sector(sector, data, true)
+    }
+
+    pub fn decrypt_sector(&self, sector: u64, data: &mut [u8]) -> Result<(), CryptoError> {
+        self.process_sector(sector, data, false)
+    }
+
+    fn process(
+        &self,
+        data: &mut [u8],
+        encrypt: bool,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), CryptoError> {
+        let len = data.len() as u64;
+        if len > 0 && len < 16 {
+            return Err(CryptoError::InvalidLength);
+        }
+        let count = Self::sector_count(len);
+        let mut sectors = Vec::with_capacity(count as usize);
+        let mut rest = data;
+        for i in 0..count {
+            let range = Self::sector_range(len, i);
+            let (sector, tail) = rest.split_at_mut((range.end - range.start) as usize);
+            sectors.push((i, sector));
+            rest = tail;
+        }
+        let tracker = Tracker::new(progress, cancel, len);
+        sectors.into_par_iter().try_for_each(|(i, sector)| {
+            tracker.check()?;
+            self.process_sector(i, sector, encrypt)?;
+            tracker.advance(sector.len() as u64)
+        })
+    }
+
+    pub fn encrypt(
+        &self,
+        data: &mut [u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), CryptoError> {
+        self.process(data, true, progress, cancel)
+    }
+
+    pub fn decrypt(
+        &self,
+        data: &mut [u8],
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), CryptoError> {
+        self.process(data, false, progress, cancel)
+    }
+
+    // Decrypts `size` bytes at `offset` of a ciphertext of length `len`,
+    // reading only the sectors that cover them
+    pub fn read_at<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        len: u64,
+        offset: u64,
+        size: usize,
+    ) -> io::Result<Vec<u8>> {
+        if len > 0 && len < 16 {
+            let msg = "shorter than an XTS block";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+        let end = offset
+            .checked_add(size as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "range past the end"))?
+            .min(len);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+        let count = Self::sector_count(len);
+        let sector_of = |pos: u64| (pos / Self::SECTOR_SIZE as u64).min(count - 1);
+        let (first, last) = (sector_of(offset), sector_of(end - 1));
+        let start = Self::sector_range(len, first).start;
+        let mut data = vec![0; (Self::sector_range(len, last).end - start) as usize];
+        reader.seek(SeekFrom::Start(start))?;
+        reader.read_exact(&mut data)?;
+
+        let mut pos = 0;
+        for i in first..=last {
+            let range = Self::sector_range(len, i);
+            let n = (range.end - range.start) as usize;
+            self.decrypt_sector(i, &mut data[pos..pos + n])?;
+            pos += n;
+        }
+        Ok(data[(offset - start) as usize..(end - start) as usize].to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::rng::OsRng;
+    use crate::crypto::symmetric_algs::{AES128, DEAL128};
+    use crate::crypto::test_util::hex;
+    use rand::random;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_xts_vectors() {
+        // IEEE 1619-2007 appendix B, vectors 2 and 15
+        let xts = XTS::<AES128>::with_keys(
+            0x1111_1111_1111_1111_1111_1111_1111_1111,
+            0x2222_2222_2222_2222_2222_2222_2222_2222,
+        )
+        .unwrap();
+        let mut data = [0x44u8; 32];
+        xts.encrypt_sector(0x33_3333_3333, &mut data).unwrap();
+        assert_eq!(
+            data.to_vec(),
+            hex("c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0")
+        );
+
+        let xts = XTS::<AES128>::with_keys(
+            0xfffe_fdfc_fbfa_f9f8_f7f6_f5f4_f3f2_f1f0,
+            0xbfbe_bdbc_bbba_b9b8_b7b6_b5b4_b3b2_b1b0,
+        )
+        .unwrap();
+        let pt = hex("000102030405060708090a0b0c0d0e0f10");
+        let mut data = pt.clone();
+        xts.encrypt_sector(0x12_3456_789a, &mut data).unwrap();
+        assert_eq!(data, hex("6c1625db4671522d3d7599601de7ca09ed"));
+        xts.decrypt_sector(0x12_3456_789a, &mut data).unwrap();
+        assert_eq!(data, pt);
+
+        // vector 1 used the same key twice, which is refused
+        assert_eq!(
+            XTS::<AES128>::with_keys(0, 0).err(),
+            Some(CryptoError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_xts_random_access() {
+        let xts = XTS::<DEAL128>::with_keys(
+            DEAL128::generate_key(&mut OsRng),
+            DEAL128::generate_key(&mut OsRng),
+        )
+        .unwrap();
+        for len in [16, 100, 4096, 4097, 4096 * 3 + 5, 4096 * 3 + 20] {
+            let data: Vec<u8> = (0..len).map(|_| random()).collect();
+            let mut enc = data.clone();
+            xts.encrypt(&mut enc, &(), &CancelToken::new()).unwrap();
+            assert_ne!(enc, data);
+
+            let mut cursor = Cursor::new(&enc);
+            for (offset, size) in [(0, len), (1, 20), (4090, 10), (len - 3, 100), (len, 1)] {
+                if offset > len {
+                    continue;
+                }
+                let part = xts
+                    .read_at(&mut cursor, len as u64, offset as u64, size)
+                    .unwrap();
+                assert_eq!(part, data[offset..len.min(offset + size)]);
+            }
+
+            xts.decrypt(&mut enc, &(), &CancelToken::new()).unwrap();
+            assert_eq!(enc, data);
+        }
+        assert!(xts.encrypt(&mut [0; 15], &(), &CancelToken::new()).is_err());
+        assert_eq!(
+            xts.encrypt_sector(0, &mut [0; 15]),
+            Err(CryptoError::InvalidLength)
+        );
+        let mut cursor = Cursor::new(vec![0; 32]);
+        assert!(xts.read_at(&mut cursor, 32, u64::MAX, 2).is_err());
+        let res = xts.read_at(&mut cursor, 15, 0, 4);
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,41 @@
+// Helpers shared by the test modules
+use crate::crypto::padding::{PaddingAlgorithm, PaddingPKSC7};
+use crate::crypto::rng::OsRng;
+use crate::crypto::symmetric_algs::{Block, BlockCipher, LegacyDEAL128};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+// one block both ways under a key, all given in hex
+pub fn check_vector<C: BlockCipher>(key: &str, input: &str, output: &str) {
+    let cipher = C::with_key(C::Key::from_bytes(&hex(key)));
+    let input = C::Block::from_bytes(&hex(input));
+    let output = C::Block::from_bytes(&hex(output));
+    assert_eq!(cipher.encrypt(input), output);
+    assert_eq!(cipher.decrypt(output), input);
+}
+
+// RDH encryption as the client did it before the container
+pub fn legacy_rdh(key: u128, iv: u128, data: &[u8]) -> Vec<u8> {
+    let mut data = data.to_vec();
+    PaddingPKSC7::with_block_size(16).apply_padding(&mut data, &mut OsRng);
+    let blocks: Vec<u128> = data.chunks(16).map(u128::from_bytes).collect();
+    let mut hasher = DefaultHasher::new();
+    blocks.hash(&mut hasher);
+    let hash = hasher.finish() as u128;
+
+    let deal = LegacyDEAL128::with_key(key);
+    let delta = iv as u64 as u128;
+    let mut out = vec![deal.encrypt(iv), deal.encrypt(hash ^ iv)];
+    for (i, b) in blocks.iter().enumerate() {
+        let offset = iv.wrapping_add(delta.wrapping_mul(i as u128 + 1));
+        out.push(deal.encrypt(b ^ offset));
+    }
+    out.iter().flat_map(|b| b.to_be_bytes()).collect()
+}
@@ -1,109 +1,167 @@
 pub mod file_exchange {
+    use s6_hcs_lib_crypto::crypto::progress::{CancelToken, Progress, Tracker};
     use std::cmp::min;
+    use std::io::{Read, Write};
     use std::net::TcpStream;
-    use std::sync::mpsc::Sender;
     use websocket::sync::Client;
     use websocket::OwnedMessage::Binary as BinMsg;
 
     pub const DATAFRAME_SIZE: usize = 1024 * 1024 * 8;
 
-    pub fn count_dataframes(file: &Vec<u8>) -> usize {
-        (if file.len() % DATAFRAME_SIZE > 0 {
+    pub fn count_dataframes(len: u64) -> usize {
+        (if len % DATAFRAME_SIZE as u64 > 0 {
             1
         } else {
             0
-        }) + file.len() / DATAFRAME_SIZE
+        }) + (len / DATAFRAME_SIZE as u64) as usize
     }
 
     pub fn send_file(
         sock: &mut Client<TcpStream>,
-        file: Vec<u8>,
-        tx: Option<Sender<Option<()>>>,
+        file: &mut dyn Read,
+        len: u64,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
     ) -> Result<(), ()> {
-        let dataframes = count_dataframes(&file);
-        if let Err(_) = sock.send_message(&BinMsg(dataframes.to_be_bytes().to_vec())) {
+        let tracker = Tracker::new(progress, cancel, len);
+        if let Err(_) = sock.send_message(&BinMsg(len.to_be_bytes().to_vec())) {
             return Err(());
         };
-        for i in 0..dataframes {
-            let lower = i * DATAFRAME_SIZE;
-            let higher = min((i + 1) * DATAFRAME_SIZE, file.len());
-            if let Err(_) = sock.send_message(&BinMsg(file[lower..higher].to_owned())) {
+        let mut left = len;
+        for _ in 0..count_dataframes(len) {
+            tracker.check().map_err(|_| ())?;
+            let mut frame = vec![0; min(DATAFRAME_SIZE as u64, left) as usize];
+            if let Err(_) = file.read_exact(&mut frame) {
                 return Err(());
             }
-            tx.clone().and_then(|t: Sender<_>| t.send(Some(())).into());
+            let size = frame.len() as u64;
+            left -= size;
+            if let Err(_) = sock.send_message(&BinMsg(frame)) {
+                return Err(());
+            }
+            tracker.advance(size).map_err(|_| ())?;
         }
-        tx.and_then(|t| t.send(None).into());
         Ok(())
     }
 
-    pub fn recv_file_len(sock: &mut Client<TcpStream>) -> Result<usize, ()> {
+    pub fn recv_file_len(sock: &mut Client<TcpStream>) -> Result<u64, ()> {
         match sock.recv_message() {
-            Ok(BinMsg(msg)) => Ok(usize::from_be_bytes(msg.try_into().unwrap())),
+            Ok(BinMsg(msg)) => match msg.try_into() {
+                Ok(len) => Ok(u64::from_be_bytes(len)),
+                Err(_) => Err(()),
+            },
             _ => return Err(()),
         }
     }
 
     pub fn recv_file(
         sock: &mut Client<TcpStream>,
-        dataframes: usize,
-        tx: Option<Sender<Option<()>>>,
-    ) -> Result<Vec<u8>, ()> {
-        let mut file = Vec::new();
-        for _ in 0..dataframes {
+        file: &mut dyn Write,
+        len: u64,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), ()> {
+        let tracker = Tracker::new(progress, cancel, len);
+        for _ in 0..count_dataframes(len) {
+            tracker.check().map_err(|_| ())?;
             match sock.recv_message() {
-                Ok(BinMsg(msg)) => file.extend(msg),
+                Ok(BinMsg(msg)) => {
+                    if let Err(_) = file.write_all(&msg) {
+                        return Err(());
+                    }
+                    tracker.advance(msg.len() as u64).map_err(|_| ())?;
+                }
                 _ => return Err(()),
             }
-            tx.clone().and_then(|t| t.send(Some(())).into());
         }
-        tx.and_then(|t| t.send(None).into());
-        Ok(file)
+        Ok(())
     }
 }
 
 pub mod key_exchange {
     use super::aux::*;
     use s6_hcs_lib_crypto::crypto::asymmetric_algs::XTR;
+    use s6_hcs_lib_crypto::crypto::progress::{CancelToken, Progress, Tracker};
+    use s6_hcs_lib_crypto::crypto::rng::SecureRng;
 
     use std::net::TcpStream;
     use websocket::sync::Client;
 
-    pub fn client_send(client: &mut Client<TcpStream>, key: u128) {
-        let xtr = XTR::new_at_client(deserialize(client.recv_message()));
-        client
-            .send_message(&serialize(xtr.share_trace_with_server()))
-            .unwrap();
-        client
-            .send_message(&serialize(xtr.encrypt_deal128_key(key)))
-            .unwrap();
-    }
+    // progress is counted in messages, every exchange takes three
+    const STEPS: u64 = 3;
 
-    pub fn server_receive(client: &mut Client<TcpStream>) -> u128 {
-        let mut xtr = XTR::new_at_server();
-        client
-            .send_message(&serialize(xtr.share_public_key_with_client()))
-            .unwrap();
+    pub fn client_send(
+        client: &mut Client<TcpStream>,
+        key: &[u8],
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), ()> {
+        let tracker = Tracker::new(progress, cancel, STEPS);
+        let xtr = XTR::new_at_client(deserialize(client.recv_message()), rng);
+        tracker.advance(1).map_err(|_| ())?;
+        let trace = serialize(xtr.share_trace_with_server());
+        client.send_message(&trace).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
+        let key = serialize(xtr.encrypt(key).map_err(|_| ())?);
+        client.send_message(&key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())
+    }
+
+    pub fn server_receive(
+        client: &mut Client<TcpStream>,
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<u8>, ()> {
+        let tracker = Tracker::new(progress, cancel, STEPS);
+        let mut xtr = XTR::new_at_server(rng);
+        let public_key = serialize(xtr.share_public_key_with_client());
+        client.send_message(&public_key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
         xtr.derive_sym_key_at_server(deserialize(client.recv_message()));
-        xtr.encrypt_deal128_key(deserialize(client.recv_message()))
-    }
-
-    pub fn server_send(client: &mut Client<TcpStream>, key: u128) {
-        let mut xtr = XTR::new_at_server();
-        client
-            .send_message(&serialize(xtr.share_public_key_with_client()))
-            .unwrap();
+        tracker.advance(1).map_err(|_| ())?;
+        let key: Vec<u8> = deserialize(client.recv_message());
+        let key = xtr.encrypt(&key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
+        Ok(key)
+    }
+
+    pub fn server_send(
+        client: &mut Client<TcpStream>,
+        key: &[u8],
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<(), ()> {
+        let tracker = Tracker::new(progress, cancel, STEPS);
+        let mut xtr = XTR::new_at_server(rng);
+        let public_key = serialize(xtr.share_public_key_with_client());
+        client.send_message(&public_key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
         xtr.derive_sym_key_at_server(deserialize(client.recv_message()));
-        client
-            .send_message(&serialize(xtr.encrypt_deal128_key(key)))
-            .unwrap();
+        tracker.advance(1).map_err(|_| ())?;
+        let key = serialize(xtr.encrypt(key).map_err(|_| ())?);
+        client.send_message(&key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())
     }
 
-    pub fn client_receive(client: &mut Client<TcpStream>) -> u128 {
-        let xtr = XTR::new_at_client(deserialize(client.recv_message()));
-        client
-            .send_message(&serialize(xtr.share_trace_with_server()))
-            .unwrap();
-        xtr.encrypt_deal128_key(deserialize(client.recv_message()))
+    pub fn client_receive(
+        client: &mut Client<TcpStream>,
+        rng: &mut dyn SecureRng,
+        progress: &dyn Progress,
+        cancel: &CancelToken,
+    ) -> Result<Vec<u8>, ()> {
+        let tracker = Tracker::new(progress, cancel, STEPS);
+        let xtr = XTR::new_at_client(deserialize(client.recv_message()), rng);
+        tracker.advance(1).map_err(|_| ())?;
+        let trace = serialize(xtr.share_trace_with_server());
+        client.send_message(&trace).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
+        let key: Vec<u8> = deserialize(client.recv_message());
+        let key = xtr.encrypt(&key).map_err(|_| ())?;
+        tracker.advance(1).map_err(|_| ())?;
+        Ok(key)
     }
 }
 
@@ -161,18 +219,4 @@ pub mod aux {
     {
         OwnedMessage::Binary(serde_json::to_vec(&data).unwrap())
     }
-
-    pub fn u128_to_u8(value: Vec<u128>) -> Vec<u8> {
-        value
-            .iter()
-            .flat_map(|num| num.to_be_bytes().to_vec())
-            .collect()
-    }
-
-    pub fn u8_to_u128(value: Vec<u8>) -> Vec<u128> {
-        value
-            .chunks(16)
-            .map(|chunk| u128::from_be_bytes(chunk.try_into().unwrap()))
-            .collect()
-    }
 }
@@ -2,12 +2,13 @@ use s6_hcs_lib_transfer::aux::FileList;
 
 use path_macro::path;
 use rand::random;
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::value::RawValue;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::{
     error::Error,
-    fs::{self},
+    fs::{self, File},
     io,
     path::Path,
 };
@@ -15,7 +16,18 @@ use std::{
 #[derive(Debug, Serialize, Deserialize)]
 struct Metadata {
     name: String,
-    key: u128,
+    #[serde(deserialize_with = "deserialize_key")]
+    key: Vec<u8>,
+}
+
+// Files uploaded before keys were byte strings keep their DEAL-128 key as a
+// number. It is read from the raw text, through serde it would pass as an f64.
+fn deserialize_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let raw = Box::<RawValue>::deserialize(deserializer)?;
+    match raw.get().parse::<u128>() {
+        Ok(key) => Ok(key.to_be_bytes().to_vec()),
+        Err(_) => serde_json::from_str(raw.get()).map_err(D::Error::custom),
+    }
 }
 
 pub struct FileManager {
@@ -34,11 +46,18 @@ impl FileManager {
         Ok(new)
     }
 
-    pub fn save_file(&self, name: String, key: u128, contents: Vec<u8>) -> io::Result<()> {
+    pub fn save_file<F>(&self, name: String, key: Vec<u8>, write: F) -> io::Result<()>
+    where
+        F: FnOnce(&mut File) -> Result<(), ()>,
+    {
         let id: u128 = random();
         let path = path!(self.dir / format!("{id}"));
         fs::create_dir_all(&path)?;
-        fs::write(&path!(path / "file"), contents)?;
+        let mut file = File::create(path!(path / "file"))?;
+        if let Err(_) = write(&mut file) {
+            fs::remove_dir_all(path).unwrap_or_default();
+            return Err(io::ErrorKind::InvalidData.into());
+        }
         let metadata = Metadata { name, key };
         let metadata_json = serde_json::to_string(&metadata)?;
         fs::write(path!(path / "metadata.json"), metadata_json)?;
@@ -77,15 +96,20 @@ impl FileManager {
         Ok(file_list)
     }
 
-    pub fn get_file(&self, id: u128) -> io::Result<(Vec<u8>, u128)> {
+    pub fn with_file<F, T>(&self, id: u128, read: F) -> io::Result<T>
+    where
+        F: FnOnce(&mut File, u64, Vec<u8>, String) -> T,
+    {
         let path = path!(self.dir / format!("{id}"));
-        fs::write(path!(path / "lock"), "")?;
-        let file = PathBuf::from(path!(path / "file"));
         let metadata = fs::read_to_string(path!(path / "metadata.json"))?;
         let metadata: Metadata = serde_json::from_str(&metadata)?;
-        let contents = fs::read(file)?;
-        fs::remove_file(path!(path / "lock"))?;
-        Ok((contents, metadata.key))
+        fs::write(path!(path / "lock"), "")?;
+        let result = File::open(path!(path / "file")).and_then(|mut file| {
+            let len = file.metadata()?.len();
+            Ok(read(&mut file, len, metadata.key, metadata.name))
+        });
+        fs::remove_file(path!(path / "lock")).unwrap_or_default();
+        result
     }
 
     pub fn delete_file(&self, id: u128) -> io::Result<()> {
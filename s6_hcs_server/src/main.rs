@@ -1,6 +1,8 @@
 mod file_manager;
 
 use file_manager::FileManager;
+use s6_hcs_lib_crypto::crypto::progress::CancelToken;
+use s6_hcs_lib_crypto::crypto::rng::OsRng;
 use s6_hcs_lib_transfer::{aux::*, file_exchange, key_exchange, messages::*};
 
 use dotenvy::{dotenv, var};
@@ -33,12 +35,23 @@ fn main() {
                 }
 
                 Upload => {
-                    let key = key_exchange::server_receive(&mut client);
+                    // the server never cancels, a client aborts by disconnecting
+                    let cancel = CancelToken::new();
+                    let key =
+                        match key_exchange::server_receive(&mut client, &mut OsRng, &(), &cancel) {
+                            Ok(key) => key,
+                            Err(_) => {
+                                log!(Level::Warn, "Key exchange failed");
+                                return;
+                            }
+                        };
                     let name = deserialize(client.recv_message());
                     log!(Level::Info, "Receiving of {}", name);
                     let size = file_exchange::recv_file_len(&mut client).unwrap();
-                    let contents = file_exchange::recv_file(&mut client, size, None).unwrap();
-                    if let Ok(()) = mgr.save_file(name, key, contents) {
+                    let saved = mgr.save_file(name, key, |file| {
+                        file_exchange::recv_file(&mut client, file, size, &(), &cancel)
+                    });
+                    if let Ok(()) = saved {
                         respond(&mut client, Success);
                     } else {
                         respond(&mut client, FSFail);
@@ -46,18 +59,18 @@ fn main() {
                 }
 
                 Download(id) => {
-                    let (contents, key) = match mgr.get_file(id) {
-                        Ok(data) => {
-                            respond(&mut client, Success);
-                            data
-                        }
-                        Err(_) => {
-                            respond(&mut client, FSFail);
-                            return;
-                        }
-                    };
-                    key_exchange::server_send(&mut client, key);
-                    file_exchange::send_file(&mut client, contents, None).unwrap();
+                    let cancel = CancelToken::new();
+                    let sent = mgr.with_file(id, |file, len, key, name| {
+                        respond(&mut client, Success);
+                        key_exchange::server_send(&mut client, &key, &mut OsRng, &(), &cancel)?;
+                        client.send_message(&serialize(name)).map_err(|_| ())?;
+                        file_exchange::send_file(&mut client, file, len, &(), &cancel)
+                    });
+                    match sent {
+                        Err(_) => respond(&mut client, FSFail),
+                        Ok(Err(_)) => log!(Level::Warn, "Sending aborted by the client"),
+                        Ok(Ok(())) => {}
+                    }
                 }
 
                 Delete(id) => {